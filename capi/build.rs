@@ -0,0 +1,34 @@
+// Generates tabstate.h from src/lib.rs into OUT_DIR. The header in include/ is checked in, so C users
+// don't need cbindgen, and tests/header.rs fails when it doesn't match the generated one. Writing into
+// the source tree would break read-only checkouts, and a header isn't worth failing the build over, so
+// problems are only warnings.
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let (Ok(crate_dir), Ok(out_dir)) = (
+        std::env::var("CARGO_MANIFEST_DIR"),
+        std::env::var("OUT_DIR"),
+    ) else {
+        println!("cargo:warning=CARGO_MANIFEST_DIR or OUT_DIR is not set, so tabstate.h was not generated");
+        return;
+    };
+    let config = match cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")) {
+        Ok(config) => config,
+        Err(e) => {
+            println!("cargo:warning=Could not read cbindgen.toml: {e}");
+            return;
+        }
+    };
+
+    match cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+    {
+        Ok(bindings) => {
+            bindings.write_to_file(format!("{out_dir}/tabstate.h"));
+        }
+        Err(e) => println!("cargo:warning=Could not generate tabstate.h: {e}"),
+    }
+}
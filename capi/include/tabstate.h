@@ -0,0 +1,333 @@
+/* Generated by cbindgen from capi/src/lib.rs. Do not edit. */
+
+#ifndef TABSTATE_H
+#define TABSTATE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The kind of the last error on this thread.
+ */
+typedef enum TabStateErrorKind {
+  TAB_STATE_ERROR_KIND_NONE = 0,
+  /**
+   * The data is not a valid TabState file.
+   */
+  TAB_STATE_ERROR_KIND_INVALID_DATA = 1,
+  /**
+   * The data looks valid, but it's a variant we don't know how to parse yet.
+   */
+  TAB_STATE_ERROR_KIND_UNSUPPORTED = 2,
+  /**
+   * The data ended before the file did.
+   */
+  TAB_STATE_ERROR_KIND_UNEXPECTED_EOF = 3,
+  /**
+   * The file could not be read.
+   */
+  TAB_STATE_ERROR_KIND_IO = 4,
+  /**
+   * A pointer argument was null, or a string argument was not valid UTF-8.
+   */
+  TAB_STATE_ERROR_KIND_INVALID_ARGUMENT = 5,
+  /**
+   * A varint in the file was too long, or too large for the field it's in.
+   */
+  TAB_STATE_ERROR_KIND_OVERFLOW = 6,
+} TabStateErrorKind;
+
+/**
+ * A parsed TabState file. Free it with `tabstate_free`.
+ */
+typedef struct TabStateHandle TabStateHandle;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Parses `len` bytes at `data` as a TabState file. Returns null on failure, and the reason can be
+ * read with `tabstate_last_error`.
+ *
+ * # Safety
+ * `data` must be null or point to `len` readable bytes.
+ */
+struct TabStateHandle *tabstate_parse(const uint8_t *data, size_t len);
+
+/**
+ * Reads and parses the TabState file at the UTF-8 `path`. Returns null on failure.
+ *
+ * # Safety
+ * `path` must be null or point to a NUL terminated string.
+ */
+struct TabStateHandle *tabstate_parse_file(const char *path);
+
+/**
+ * Frees a handle returned by `tabstate_parse` or `tabstate_parse_file`. Null is ignored.
+ *
+ * # Safety
+ * `handle` must be null or a handle from `tabstate_parse` or `tabstate_parse_file` that
+ * hasn't been freed yet. Nothing can use it, or the pointers it handed out, afterwards.
+ */
+void tabstate_free(struct TabStateHandle *handle);
+
+/**
+ * The message for the last error on this thread, or null if the last call succeeded. Every other
+ * function but `tabstate_last_error_kind` clears it first. The pointer is valid until the next call
+ * into this library on the same thread.
+ */
+const char *tabstate_last_error(void);
+
+/**
+ * The kind of the last error on this thread.
+ */
+enum TabStateErrorKind tabstate_last_error_kind(void);
+
+/**
+ * Whether the tab has been saved to a file. Unsaved tabs have no path, encoding, carriage type,
+ * filetime or hash. False if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+bool tabstate_is_saved(const struct TabStateHandle *handle);
+
+/**
+ * The unknown third byte of the header, which seems to be a version. 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+uint8_t tabstate_header_unk(const struct TabStateHandle *handle);
+
+/**
+ * The UTF-8 path of the saved file, or null for unsaved tabs or a null `handle`. `out_len` can be
+ * null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ * `out_len` must be null or point to a writable `usize`.
+ */
+const char *tabstate_path(const struct TabStateHandle *handle, size_t *out_len);
+
+/**
+ * The path length in UTF-16 chars, as stored in the file. 0 for unsaved tabs or a null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_path_len(const struct TabStateHandle *handle);
+
+/**
+ * The size in chars of the text of the file on disk, as stored in the file. 0 for unsaved tabs or a
+ * null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_full_buffer_size(const struct TabStateHandle *handle);
+
+/**
+ * The raw encoding byte, or -1 for unsaved tabs or a null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+int32_t tabstate_encoding(const struct TabStateHandle *handle);
+
+/**
+ * The raw carriage type byte, or -1 for unsaved tabs or a null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+int32_t tabstate_carriage_type(const struct TabStateHandle *handle);
+
+/**
+ * The FILETIME of the saved file, or 0 for unsaved tabs or a null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+uint64_t tabstate_filetime(const struct TabStateHandle *handle);
+
+/**
+ * The 32 byte hash of the saved file, or null for unsaved tabs or a null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+const uint8_t *tabstate_content_hash(const struct TabStateHandle *handle);
+
+/**
+ * The unknown byte after the hash, or -1 for unsaved tabs or a null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+int32_t tabstate_metadata_unk(const struct TabStateHandle *handle);
+
+/**
+ * The unknown last byte of the metadata, or -1 for unsaved tabs or a null `handle`.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+int32_t tabstate_metadata_unk2(const struct TabStateHandle *handle);
+
+/**
+ * 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_cursor_start(const struct TabStateHandle *handle);
+
+/**
+ * 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_cursor_end(const struct TabStateHandle *handle);
+
+/**
+ * False if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+bool tabstate_word_wrap(const struct TabStateHandle *handle);
+
+/**
+ * False if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+bool tabstate_right_to_left(const struct TabStateHandle *handle);
+
+/**
+ * False if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+bool tabstate_show_unicode_control(const struct TabStateHandle *handle);
+
+/**
+ * The raw unknown option byte, the last of the four. 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+uint8_t tabstate_options_unk(const struct TabStateHandle *handle);
+
+/**
+ * The UTF-8 text, as it was when the footer was written. The text may contain NULs, so use
+ * `out_len` if that matters. `out_len` can be null. Null if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ * `out_len` must be null or point to a writable `usize`.
+ */
+const char *tabstate_text(const struct TabStateHandle *handle, size_t *out_len);
+
+/**
+ * The text length in UTF-16 chars, as stored in the file. 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_text_len(const struct TabStateHandle *handle);
+
+/**
+ * The byte before the footer CRC, which has only ever been 0. 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+uint8_t tabstate_the_number_zero(const struct TabStateHandle *handle);
+
+/**
+ * The footer CRC. 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+uint32_t tabstate_crc(const struct TabStateHandle *handle);
+
+/**
+ * The text with every edit chunk applied, as UTF-8. Like `tabstate_text`, it lives as long as the
+ * handle. `out_len` can be null. Null if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ * `out_len` must be null or point to a writable `usize`.
+ */
+const char *tabstate_reconstructed_text(const struct TabStateHandle *handle, size_t *out_len);
+
+/**
+ * The number of edit chunks after the footer. 0 if `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_chunk_count(const struct TabStateHandle *handle);
+
+/**
+ * The char position of chunk `index`. Returns 0 if `index` is out of range or `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_chunk_position(const struct TabStateHandle *handle, size_t index);
+
+/**
+ * The amount of chars chunk `index` deleted. Returns 0 if `index` is out of range or `handle` is
+ * null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_chunk_deletion_count(const struct TabStateHandle *handle, size_t index);
+
+/**
+ * The amount of UTF-16 chars chunk `index` added, as stored in the file. Returns 0 if `index` is out
+ * of range or `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+size_t tabstate_chunk_addition_count(const struct TabStateHandle *handle,
+                                     size_t index);
+
+/**
+ * The UTF-8 text chunk `index` added, or null if `index` is out of range or `handle` is null.
+ * `out_len` can be null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ * `out_len` must be null or point to a writable `usize`.
+ */
+const char *tabstate_chunk_added_text(const struct TabStateHandle *handle,
+                                      size_t index,
+                                      size_t *out_len);
+
+/**
+ * The CRC of chunk `index`. Returns 0 if `index` is out of range or `handle` is null.
+ *
+ * # Safety
+ * `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+ */
+uint32_t tabstate_chunk_crc(const struct TabStateHandle *handle, size_t index);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* TABSTATE_H */
@@ -0,0 +1,509 @@
+#![doc = "C API for tabstate-util. Files are parsed into an opaque `TabStateHandle` that wraps the owned `TabState` model. The header is generated into `include/tabstate.h`."]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use tabstate::error::ErrorKind;
+use tabstate::tabstate::chunk::EditChunk;
+use tabstate::tabstate::TabState;
+
+/// The kind of the last error on this thread.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TabStateErrorKind {
+    None = 0,
+    /// The data is not a valid TabState file.
+    InvalidData = 1,
+    /// The data looks valid, but it's a variant we don't know how to parse yet.
+    Unsupported = 2,
+    /// The data ended before the file did.
+    UnexpectedEof = 3,
+    /// The file could not be read.
+    Io = 4,
+    /// A pointer argument was null, or a string argument was not valid UTF-8.
+    InvalidArgument = 5,
+    /// A varint in the file was too long, or too large for the field it's in.
+    Overflow = 6,
+}
+
+/// A parsed TabState file. Free it with `tabstate_free`.
+pub struct TabStateHandle {
+    tab: TabState,
+    /// NUL terminated copies of the strings, so we can hand out pointers that live as long as the
+    /// handle.
+    path: Option<Vec<u8>>,
+    text: Vec<u8>,
+    reconstructed_text: Vec<u8>,
+    chunk_texts: Vec<Vec<u8>>,
+}
+
+impl TabStateHandle {
+    fn new(tab: TabState) -> Self {
+        Self {
+            path: tab.metadata.as_ref().map(|m| nul_terminated(&m.path)),
+            text: nul_terminated(&tab.text),
+            reconstructed_text: nul_terminated(&tab.reconstruct_text()),
+            chunk_texts: tab
+                .chunks
+                .iter()
+                .map(|c| nul_terminated(&c.added_text))
+                .collect(),
+            tab,
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(TabStateErrorKind, CString)>> = const { RefCell::new(None) };
+}
+
+fn set_error(kind: TabStateErrorKind, message: impl Into<Vec<u8>>) {
+    let mut message = message.into();
+    message.retain(|b| *b != 0);
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some((kind, message)));
+}
+
+fn clear_error() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
+
+fn nul_terminated(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(s.len() + 1);
+    bytes.extend_from_slice(s.as_bytes());
+    bytes.push(0);
+    bytes
+}
+
+/// Hands out a pointer to a NUL terminated string, and its length without the NUL.
+unsafe fn out_str(bytes: &[u8], out_len: *mut usize) -> *const c_char {
+    if !out_len.is_null() {
+        *out_len = bytes.len() - 1;
+    }
+    bytes.as_ptr() as *const c_char
+}
+
+fn parse(buffer: &[u8]) -> *mut TabStateHandle {
+    match TabState::from_buffer(buffer) {
+        Ok(tab) => Box::into_raw(Box::new(TabStateHandle::new(tab))),
+        Err(e) => {
+            let kind = match e.kind() {
+                ErrorKind::InvalidData => TabStateErrorKind::InvalidData,
+                ErrorKind::Unsupported => TabStateErrorKind::Unsupported,
+                ErrorKind::UnexpectedEof => TabStateErrorKind::UnexpectedEof,
+                ErrorKind::Overflow => TabStateErrorKind::Overflow,
+            };
+            set_error(kind, e.message());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Parses `len` bytes at `data` as a TabState file. Returns null on failure, and the reason can be
+/// read with `tabstate_last_error`.
+///
+/// # Safety
+/// `data` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_parse(data: *const u8, len: usize) -> *mut TabStateHandle {
+    clear_error();
+    if data.is_null() {
+        set_error(TabStateErrorKind::InvalidArgument, "data is null");
+        return ptr::null_mut();
+    }
+    parse(std::slice::from_raw_parts(data, len))
+}
+
+/// Reads and parses the TabState file at the UTF-8 `path`. Returns null on failure.
+///
+/// # Safety
+/// `path` must be null or point to a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_parse_file(path: *const c_char) -> *mut TabStateHandle {
+    clear_error();
+    if path.is_null() {
+        set_error(TabStateErrorKind::InvalidArgument, "path is null");
+        return ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        set_error(
+            TabStateErrorKind::InvalidArgument,
+            "path is not valid UTF-8",
+        );
+        return ptr::null_mut();
+    };
+    match std::fs::read(path) {
+        Ok(buffer) => parse(&buffer),
+        Err(e) => {
+            set_error(TabStateErrorKind::Io, e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a handle returned by `tabstate_parse` or `tabstate_parse_file`. Null is ignored.
+///
+/// # Safety
+/// `handle` must be null or a handle from `tabstate_parse` or `tabstate_parse_file` that
+/// hasn't been freed yet. Nothing can use it, or the pointers it handed out, afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_free(handle: *mut TabStateHandle) {
+    clear_error();
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// The message for the last error on this thread, or null if the last call succeeded. Every other
+/// function but `tabstate_last_error_kind` clears it first. The pointer is valid until the next call
+/// into this library on the same thread.
+#[no_mangle]
+pub extern "C" fn tabstate_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some((_, message)) => message.as_ptr(),
+        None => ptr::null(),
+    })
+}
+
+/// The kind of the last error on this thread.
+#[no_mangle]
+pub extern "C" fn tabstate_last_error_kind() -> TabStateErrorKind {
+    LAST_ERROR.with(|e| match &*e.borrow() {
+        Some((kind, _)) => *kind,
+        None => TabStateErrorKind::None,
+    })
+}
+
+/// Gets the handle behind the pointer. Every accessor starts here, so this is also where their
+/// error from the last call is cleared. A null handle sets an `InvalidArgument` error, and the
+/// accessors return their default for it, which each one documents.
+unsafe fn get<'a>(handle: *const TabStateHandle) -> Option<&'a TabStateHandle> {
+    clear_error();
+    if handle.is_null() {
+        set_error(TabStateErrorKind::InvalidArgument, "handle is null");
+        return None;
+    }
+    Some(&*handle)
+}
+
+/// Whether the tab has been saved to a file. Unsaved tabs have no path, encoding, carriage type,
+/// filetime or hash. False if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_is_saved(handle: *const TabStateHandle) -> bool {
+    get(handle).is_some_and(|h| h.tab.metadata.is_some())
+}
+
+/// The unknown third byte of the header, which seems to be a version. 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_header_unk(handle: *const TabStateHandle) -> u8 {
+    get(handle).map_or(0, |h| h.tab.header_unk)
+}
+
+/// The UTF-8 path of the saved file, or null for unsaved tabs or a null `handle`. `out_len` can be
+/// null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+/// `out_len` must be null or point to a writable `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_path(
+    handle: *const TabStateHandle,
+    out_len: *mut usize,
+) -> *const c_char {
+    match get(handle).and_then(|h| h.path.as_ref()) {
+        Some(path) => out_str(path, out_len),
+        None => ptr::null(),
+    }
+}
+
+/// The path length in UTF-16 chars, as stored in the file. 0 for unsaved tabs or a null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_path_len(handle: *const TabStateHandle) -> usize {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .and_then(|m| m.path_len)
+        .unwrap_or_default()
+}
+
+/// The size in chars of the text of the file on disk, as stored in the file. 0 for unsaved tabs or a
+/// null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_full_buffer_size(handle: *const TabStateHandle) -> usize {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .and_then(|m| m.full_buffer_size)
+        .unwrap_or_default()
+}
+
+/// The raw encoding byte, or -1 for unsaved tabs or a null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_encoding(handle: *const TabStateHandle) -> i32 {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .map_or(-1, |m| m.encoding.as_value() as i32)
+}
+
+/// The raw carriage type byte, or -1 for unsaved tabs or a null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_carriage_type(handle: *const TabStateHandle) -> i32 {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .map_or(-1, |m| m.carriage_type.as_value() as i32)
+}
+
+/// The FILETIME of the saved file, or 0 for unsaved tabs or a null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_filetime(handle: *const TabStateHandle) -> u64 {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .map_or(0, |m| m.filetime)
+}
+
+/// The 32 byte hash of the saved file, or null for unsaved tabs or a null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_content_hash(handle: *const TabStateHandle) -> *const u8 {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .map_or(ptr::null(), |m| m.content_hash.as_ptr())
+}
+
+/// The unknown byte after the hash, or -1 for unsaved tabs or a null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_metadata_unk(handle: *const TabStateHandle) -> i32 {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .map_or(-1, |m| m.unk as i32)
+}
+
+/// The unknown last byte of the metadata, or -1 for unsaved tabs or a null `handle`.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_metadata_unk2(handle: *const TabStateHandle) -> i32 {
+    get(handle)
+        .and_then(|h| h.tab.metadata.as_ref())
+        .map_or(-1, |m| m.unk2 as i32)
+}
+
+/// 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_cursor_start(handle: *const TabStateHandle) -> usize {
+    get(handle).map_or(0, |h| h.tab.cursor_start)
+}
+
+/// 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_cursor_end(handle: *const TabStateHandle) -> usize {
+    get(handle).map_or(0, |h| h.tab.cursor_end)
+}
+
+/// False if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_word_wrap(handle: *const TabStateHandle) -> bool {
+    get(handle).is_some_and(|h| h.tab.options.word_wrap())
+}
+
+/// False if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_right_to_left(handle: *const TabStateHandle) -> bool {
+    get(handle).is_some_and(|h| h.tab.options.right_to_left())
+}
+
+/// False if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_show_unicode_control(handle: *const TabStateHandle) -> bool {
+    get(handle).is_some_and(|h| h.tab.options.show_unicode_control())
+}
+
+/// The raw unknown option byte, the last of the four. 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_options_unk(handle: *const TabStateHandle) -> u8 {
+    get(handle).map_or(0, |h| h.tab.options.as_bytes()[3])
+}
+
+/// The UTF-8 text, as it was when the footer was written. The text may contain NULs, so use
+/// `out_len` if that matters. `out_len` can be null. Null if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+/// `out_len` must be null or point to a writable `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_text(
+    handle: *const TabStateHandle,
+    out_len: *mut usize,
+) -> *const c_char {
+    match get(handle) {
+        Some(h) => out_str(&h.text, out_len),
+        None => ptr::null(),
+    }
+}
+
+/// The text length in UTF-16 chars, as stored in the file. 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_text_len(handle: *const TabStateHandle) -> usize {
+    get(handle).and_then(|h| h.tab.text_len).unwrap_or_default()
+}
+
+/// The byte before the footer CRC, which has only ever been 0. 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_the_number_zero(handle: *const TabStateHandle) -> u8 {
+    get(handle).map_or(0, |h| h.tab.the_number_zero)
+}
+
+/// The footer CRC. 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_crc(handle: *const TabStateHandle) -> u32 {
+    get(handle).and_then(|h| h.tab.crc).unwrap_or_default()
+}
+
+/// The text with every edit chunk applied, as UTF-8. Like `tabstate_text`, it lives as long as the
+/// handle. `out_len` can be null. Null if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+/// `out_len` must be null or point to a writable `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_reconstructed_text(
+    handle: *const TabStateHandle,
+    out_len: *mut usize,
+) -> *const c_char {
+    match get(handle) {
+        Some(h) => out_str(&h.reconstructed_text, out_len),
+        None => ptr::null(),
+    }
+}
+
+/// The number of edit chunks after the footer. 0 if `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_chunk_count(handle: *const TabStateHandle) -> usize {
+    get(handle).map_or(0, |h| h.tab.chunks.len())
+}
+
+/// The char position of chunk `index`. Returns 0 if `index` is out of range or `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_chunk_position(
+    handle: *const TabStateHandle,
+    index: usize,
+) -> usize {
+    chunk(handle, index).map_or(0, |c| c.position)
+}
+
+/// The amount of chars chunk `index` deleted. Returns 0 if `index` is out of range or `handle` is
+/// null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_chunk_deletion_count(
+    handle: *const TabStateHandle,
+    index: usize,
+) -> usize {
+    chunk(handle, index).map_or(0, |c| c.deletion_count)
+}
+
+/// The amount of UTF-16 chars chunk `index` added, as stored in the file. Returns 0 if `index` is out
+/// of range or `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_chunk_addition_count(
+    handle: *const TabStateHandle,
+    index: usize,
+) -> usize {
+    chunk(handle, index)
+        .and_then(|c| c.addition_count)
+        .unwrap_or_default()
+}
+
+/// The UTF-8 text chunk `index` added, or null if `index` is out of range or `handle` is null.
+/// `out_len` can be null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+/// `out_len` must be null or point to a writable `usize`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_chunk_added_text(
+    handle: *const TabStateHandle,
+    index: usize,
+    out_len: *mut usize,
+) -> *const c_char {
+    match get(handle).and_then(|h| h.chunk_texts.get(index)) {
+        Some(text) => out_str(text, out_len),
+        None => ptr::null(),
+    }
+}
+
+/// The CRC of chunk `index`. Returns 0 if `index` is out of range or `handle` is null.
+///
+/// # Safety
+/// `handle` must be null or a live handle from `tabstate_parse` or `tabstate_parse_file`.
+#[no_mangle]
+pub unsafe extern "C" fn tabstate_chunk_crc(handle: *const TabStateHandle, index: usize) -> u32 {
+    chunk(handle, index).and_then(|c| c.crc).unwrap_or_default()
+}
+
+unsafe fn chunk<'a>(handle: *const TabStateHandle, index: usize) -> Option<&'a EditChunk> {
+    get(handle).and_then(|h| h.tab.chunks.get(index))
+}
@@ -0,0 +1,55 @@
+//! Builds tests/harness.c against the static library and runs it.
+
+use std::path::PathBuf;
+use std::process::Command;
+use tabstate::tabstate::chunk::EditChunk;
+use tabstate::tabstate::TabState;
+use tabstate::test_util::saved_tab;
+
+#[cfg(unix)]
+#[test]
+fn c_harness() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let tmp_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    // The test runs from target/{profile}/deps, which is where cargo puts the static library when it
+    // builds it for the tests.
+    let lib_dir = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_path_buf();
+
+    // The shared saved tab, with some non-ASCII text and a chunk, so the C side has to convert both.
+    let tab = TabState {
+        text: "Hellö".to_string(),
+        chunks: vec![EditChunk {
+            position: 5,
+            deletion_count: 0,
+            addition_count: None,
+            added_text: " World".to_string(),
+            crc: None,
+        }],
+        ..saved_tab()
+    };
+    let tab_path = tmp_dir.join("harness.bin");
+    std::fs::write(&tab_path, tab.to_bytes()).unwrap();
+
+    let exe = tmp_dir.join("harness");
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(manifest_dir.join("tests/harness.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg(lib_dir.join("libtabstate_capi.a"))
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&exe)
+        .status()
+        .expect("Could not run the C compiler");
+    assert!(status.success(), "Could not build the C harness");
+
+    let output = Command::new(&exe).arg(&tab_path).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -0,0 +1,91 @@
+/* Exercises the C API. Takes the path of a saved TabState file with one edit chunk, see c_harness.rs. */
+#include <stdio.h>
+#include <string.h>
+
+#include "tabstate.h"
+
+#define CHECK(cond)                                                        \
+    do {                                                                   \
+        if (!(cond)) {                                                     \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+            return 1;                                                      \
+        }                                                                  \
+    } while (0)
+
+int main(int argc, char **argv) {
+    CHECK(argc == 2);
+
+    /* Errors */
+    const uint8_t bad[] = {'N', 'O', 'P', 'E', 0, 0, 0, 0};
+    CHECK(tabstate_parse(bad, sizeof(bad)) == NULL);
+    CHECK(tabstate_last_error() != NULL);
+    CHECK(tabstate_last_error_kind() == TAB_STATE_ERROR_KIND_INVALID_DATA);
+    CHECK(tabstate_parse(NULL, 0) == NULL);
+    CHECK(tabstate_last_error_kind() == TAB_STATE_ERROR_KIND_INVALID_ARGUMENT);
+    CHECK(tabstate_parse_file("does/not/exist.bin") == NULL);
+    CHECK(tabstate_last_error_kind() == TAB_STATE_ERROR_KIND_IO);
+    tabstate_free(NULL);
+    CHECK(tabstate_last_error() == NULL);
+    CHECK(tabstate_last_error_kind() == TAB_STATE_ERROR_KIND_NONE);
+
+    /* Null handles give the defaults, and an error */
+    CHECK(!tabstate_is_saved(NULL));
+    CHECK(tabstate_last_error_kind() == TAB_STATE_ERROR_KIND_INVALID_ARGUMENT);
+    CHECK(tabstate_path(NULL, NULL) == NULL);
+    CHECK(tabstate_encoding(NULL) == -1);
+    CHECK(tabstate_text(NULL, NULL) == NULL);
+    CHECK(tabstate_chunk_count(NULL) == 0);
+    CHECK(tabstate_chunk_added_text(NULL, 0, NULL) == NULL);
+
+    /* Fields */
+    TabStateHandle *tab = tabstate_parse_file(argv[1]);
+    CHECK(tab != NULL);
+    CHECK(tabstate_last_error() == NULL);
+    CHECK(tabstate_is_saved(tab));
+    CHECK(tabstate_header_unk(tab) == 0);
+
+    /* A successful call clears the error from the one before */
+    CHECK(tabstate_encoding(NULL) == -1);
+    CHECK(tabstate_last_error() != NULL);
+    CHECK(tabstate_is_saved(tab));
+    CHECK(tabstate_last_error() == NULL);
+    CHECK(tabstate_last_error_kind() == TAB_STATE_ERROR_KIND_NONE);
+
+    size_t len = 0;
+    const char *path = tabstate_path(tab, &len);
+    CHECK(path != NULL && strcmp(path, "C:\\Users\\Nord\\Desktop\\test.txt") == 0 && len == 30);
+    CHECK(tabstate_path_len(tab) == 30);
+    CHECK(tabstate_full_buffer_size(tab) == 5);
+    CHECK(tabstate_encoding(tab) == 5);
+    CHECK(tabstate_carriage_type(tab) == 3);
+    CHECK(tabstate_filetime(tab) == 133450000000000000ULL);
+    CHECK(tabstate_content_hash(tab)[0] == 0xAB);
+    CHECK(tabstate_metadata_unk(tab) == 0);
+    CHECK(tabstate_metadata_unk2(tab) == 1);
+    CHECK(tabstate_cursor_start(tab) == 3);
+    CHECK(tabstate_cursor_end(tab) == 5);
+    CHECK(tabstate_word_wrap(tab));
+    CHECK(!tabstate_right_to_left(tab));
+    CHECK(!tabstate_show_unicode_control(tab));
+    CHECK(tabstate_options_unk(tab) == 1);
+    CHECK(tabstate_the_number_zero(tab) == 0);
+    CHECK(tabstate_crc(tab) != 0);
+
+    const char *text = tabstate_text(tab, &len);
+    CHECK(strcmp(text, "Hell\xC3\xB6") == 0 && len == 6);
+    CHECK(tabstate_text_len(tab) == 5);
+    CHECK(strcmp(tabstate_reconstructed_text(tab, NULL), "Hell\xC3\xB6 World") == 0);
+
+    CHECK(tabstate_chunk_count(tab) == 1);
+    CHECK(tabstate_chunk_position(tab, 0) == 5);
+    CHECK(tabstate_chunk_deletion_count(tab, 0) == 0);
+    CHECK(tabstate_chunk_addition_count(tab, 0) == 6);
+    CHECK(tabstate_chunk_crc(tab, 0) != 0);
+    CHECK(tabstate_chunk_crc(tab, 1) == 0);
+    CHECK(strcmp(tabstate_chunk_added_text(tab, 0, NULL), " World") == 0);
+    CHECK(tabstate_chunk_added_text(tab, 1, NULL) == NULL);
+
+    tabstate_free(tab);
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,17 @@
+//! Checks that the checked in include/tabstate.h matches the one build.rs generates from src/lib.rs.
+
+#[test]
+fn header_is_up_to_date() {
+    let generated_path = concat!(env!("OUT_DIR"), "/tabstate.h");
+    let generated = std::fs::read_to_string(generated_path)
+        .expect("build.rs didn't generate the header, see the build warnings");
+    let committed =
+        std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/include/tabstate.h"))
+            .unwrap();
+
+    // Git may have checked the header out with CRLF line breaks.
+    assert!(
+        generated == committed.replace("\r\n", "\n"),
+        "include/tabstate.h is out of date. Copy {generated_path} over it."
+    );
+}
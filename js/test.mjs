@@ -0,0 +1,76 @@
+// Runs the wasm build under Node. Build it first, see the WebAssembly section in the README.
+import assert from "node:assert/strict";
+import { test } from "node:test";
+import { crc32 } from "node:zlib";
+import { parseTabState, parseWindowState, reconstructText, getFieldSpans } from "../pkg/tabstate_util.js";
+
+function beU32(n) {
+    return [(n >>> 24) & 0xff, (n >>> 16) & 0xff, (n >>> 8) & 0xff, n & 0xff];
+}
+
+function utf16le(s) {
+    return [...Buffer.from(s, "utf16le")];
+}
+
+function unsavedTab(text, chunks = []) {
+    const body = [0, 0, 2, 3, 1, 0, 0, 1, text.length, ...utf16le(text), 0];
+    const bytes = [0x4e, 0x50, ...body, ...beU32(crc32(Buffer.from(body)))];
+    for (const [position, deleted, added] of chunks) {
+        const chunk = [position, deleted, added.length, ...utf16le(added)];
+        bytes.push(...chunk, ...beU32(crc32(Buffer.from(chunk))));
+    }
+    return new Uint8Array(bytes);
+}
+
+test("parseTabState", () => {
+    const tab = parseTabState(unsavedTab("Hello", [[5, 0, " World"]]));
+    assert.equal(tab.text, "Hello");
+    assert.equal(tab.cursor_start, 2);
+    assert.equal(tab.cursor_end, 3);
+    assert.equal(tab.options.word_wrap, 1);
+    assert.equal(tab.chunks[0].added_text, " World");
+    assert.equal(reconstructText(unsavedTab("Hello", [[5, 0, " World"]])), "Hello World");
+});
+
+function varint(n) {
+    const bytes = [];
+    for (n = BigInt(n); n >= 0x80n; n >>= 7n) {
+        bytes.push(Number(n & 0x7fn) | 0x80);
+    }
+    return [...bytes, Number(n)];
+}
+
+function savedTab(filetime) {
+    const path = "C:\\a.txt";
+    const metadata = [path.length, ...utf16le(path), 2, 5, 3, ...varint(filetime), ...Array(32).fill(0xab), 0, 1];
+    const body = [0, 1, ...metadata, 0, 0, 1, 0, 0, 1, 2, ...utf16le("Hi"), 0];
+    return new Uint8Array([0x4e, 0x50, ...body, ...beU32(crc32(Buffer.from(body)))]);
+}
+
+test("filetime is always a BigInt", () => {
+    assert.equal(parseTabState(savedTab(1)).metadata.filetime, 1n);
+    assert.equal(parseTabState(savedTab(133450000000000000n)).metadata.filetime, 133450000000000000n);
+});
+
+test("parseTabState throws on bad input", () => {
+    assert.throws(() => parseTabState(new Uint8Array([0x4e, 0x4f])));
+});
+
+test("getFieldSpans covers the file", () => {
+    const bytes = unsavedTab("Hi");
+    const spans = getFieldSpans(bytes);
+    assert.equal(spans.reduce((n, s) => n + s.len, 0), bytes.length);
+});
+
+test("parseWindowState", () => {
+    const guid = [...Array(16).keys()];
+    const u32s = [1, 2, 3, 4, 5, 6].flatMap((n) => [n, 0, 0, 0]);
+    const body = [0, 1, ...guid, 0, ...u32s, 0];
+    const head = [7, body.length];
+    const bytes = [0x4e, 0x50, ...head, ...body, ...beU32(crc32(Buffer.from([...head, ...body])))];
+
+    const window = parseWindowState(new Uint8Array(bytes));
+    assert.equal(window.sequence_number, 7);
+    assert.deepEqual(window.tabs, ["03020100-0504-0706-0809-0a0b0c0d0e0f"]);
+    assert.deepEqual(window.window_size, [5, 6]);
+});
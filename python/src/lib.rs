@@ -0,0 +1,316 @@
+#![doc = "Python bindings for tabstate-util. Every class wraps the owned Rust model, so the attributes are the same fields you get from `TabState` and `WindowState` in Rust."]
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use std::path::PathBuf;
+use tabstate::dir::{get_tabstate_dir, get_windowstate_dir, read_session, TabStateFile};
+use tabstate::tabstate::chunk::EditChunk;
+use tabstate::tabstate::TabState;
+use tabstate::windowstate::WindowState;
+
+/// Display options for a tab.
+#[pyclass(name = "Options", frozen, get_all)]
+#[derive(Clone)]
+struct PyOptions {
+    word_wrap: bool,
+    right_to_left: bool,
+    show_unicode_control: bool,
+}
+
+#[pymethods]
+impl PyOptions {
+    fn __repr__(&self) -> String {
+        format!(
+            "Options(word_wrap={}, right_to_left={}, show_unicode_control={})",
+            py_bool(self.word_wrap),
+            py_bool(self.right_to_left),
+            py_bool(self.show_unicode_control)
+        )
+    }
+}
+
+/// An edit Notepad appended after the footer.
+#[pyclass(name = "EditChunk", frozen)]
+#[derive(Clone)]
+struct PyEditChunk {
+    inner: EditChunk,
+}
+
+#[pymethods]
+impl PyEditChunk {
+    #[getter]
+    fn position(&self) -> usize {
+        self.inner.position
+    }
+    #[getter]
+    fn deletion_count(&self) -> usize {
+        self.inner.deletion_count
+    }
+    #[getter]
+    fn added_text(&self) -> &str {
+        &self.inner.added_text
+    }
+    #[getter]
+    fn crc(&self) -> Option<u32> {
+        self.inner.crc
+    }
+    fn __repr__(&self) -> String {
+        format!(
+            "EditChunk(position={}, deletion_count={}, added_text={:?})",
+            self.inner.position, self.inner.deletion_count, self.inner.added_text
+        )
+    }
+}
+
+/// A parsed TabState file. `path`, `encoding`, `carriage_type`, `filetime` and `content_hash` are
+/// `None` for unsaved tabs.
+#[pyclass(name = "TabState", frozen)]
+#[derive(Clone)]
+struct PyTabState {
+    inner: TabState,
+}
+
+#[pymethods]
+impl PyTabState {
+    #[getter]
+    fn is_saved(&self) -> bool {
+        self.inner.metadata.is_some()
+    }
+    #[getter]
+    fn path(&self) -> Option<&str> {
+        self.inner.metadata.as_ref().map(|m| m.path.as_str())
+    }
+    /// The name of the encoding, like `UTF8`. `Display` gives the number, so this uses `Debug`.
+    #[getter]
+    fn encoding(&self) -> Option<String> {
+        self.inner
+            .metadata
+            .as_ref()
+            .map(|m| format!("{:?}", m.encoding))
+    }
+    /// The name of the carriage type, like `CRLF`.
+    #[getter]
+    fn carriage_type(&self) -> Option<String> {
+        self.inner
+            .metadata
+            .as_ref()
+            .map(|m| format!("{:?}", m.carriage_type))
+    }
+    /// The raw FILETIME of the file on disk.
+    #[getter]
+    fn filetime(&self) -> Option<u64> {
+        self.inner.metadata.as_ref().map(|m| m.filetime)
+    }
+    #[getter]
+    fn content_hash<'py>(&self, py: Python<'py>) -> Option<&'py PyBytes> {
+        self.inner
+            .metadata
+            .as_ref()
+            .map(|m| PyBytes::new(py, &m.content_hash))
+    }
+    /// The selection as `(start, end)`. They are the same when nothing is selected.
+    #[getter]
+    fn cursor(&self) -> (usize, usize) {
+        (self.inner.cursor_start, self.inner.cursor_end)
+    }
+    #[getter]
+    fn options(&self) -> PyOptions {
+        let options = &self.inner.options;
+        PyOptions {
+            word_wrap: options.word_wrap(),
+            right_to_left: options.right_to_left(),
+            show_unicode_control: options.show_unicode_control(),
+        }
+    }
+    #[getter]
+    fn text(&self) -> &str {
+        &self.inner.text
+    }
+    #[getter]
+    fn chunks(&self) -> Vec<PyEditChunk> {
+        self.inner
+            .chunks
+            .iter()
+            .map(|c| PyEditChunk { inner: c.clone() })
+            .collect()
+    }
+    #[getter]
+    fn crc(&self) -> Option<u32> {
+        self.inner.crc
+    }
+    /// The text with every edit chunk applied, as Notepad would show it.
+    fn reconstruct_text(&self) -> String {
+        self.inner.reconstruct_text()
+    }
+    fn to_bytes<'py>(&self, py: Python<'py>) -> &'py PyBytes {
+        PyBytes::new(py, &self.inner.to_bytes())
+    }
+    fn __repr__(&self) -> String {
+        format!(
+            "TabState(path={:?}, text_len={}, chunks={})",
+            self.path(),
+            self.inner.text.encode_utf16().count(),
+            self.inner.chunks.len()
+        )
+    }
+}
+
+/// A parsed WindowState file.
+#[pyclass(name = "WindowState", frozen)]
+#[derive(Clone)]
+struct PyWindowState {
+    inner: WindowState,
+}
+
+#[pymethods]
+impl PyWindowState {
+    #[getter]
+    fn sequence_number(&self) -> usize {
+        self.inner.sequence_number
+    }
+    /// The GUIDs of the tabs in the window, in the order they are shown.
+    #[getter]
+    fn tabs(&self) -> Vec<String> {
+        self.inner.tabs.iter().map(|g| g.to_string()).collect()
+    }
+    #[getter]
+    fn active_tab(&self) -> usize {
+        self.inner.active_tab
+    }
+    #[getter]
+    fn top_left(&self) -> (u32, u32) {
+        (self.inner.top_left[0], self.inner.top_left[1])
+    }
+    #[getter]
+    fn bottom_right(&self) -> (u32, u32) {
+        (self.inner.bottom_right[0], self.inner.bottom_right[1])
+    }
+    #[getter]
+    fn window_size(&self) -> (u32, u32) {
+        (self.inner.window_size[0], self.inner.window_size[1])
+    }
+    #[getter]
+    fn crc(&self) -> Option<u32> {
+        self.inner.crc
+    }
+    fn __repr__(&self) -> String {
+        format!(
+            "WindowState(sequence_number={}, tabs={:?}, active_tab={})",
+            self.inner.sequence_number,
+            self.tabs(),
+            self.inner.active_tab
+        )
+    }
+}
+
+/// A TabState file from a session. If the file could not be parsed, `tab` is `None` and `error`
+/// says why.
+#[pyclass(name = "TabStateFile", frozen, get_all)]
+#[derive(Clone)]
+struct PyTabStateFile {
+    path: PathBuf,
+    tab: Option<PyTabState>,
+    error: Option<String>,
+}
+
+impl From<TabStateFile> for PyTabStateFile {
+    fn from(file: TabStateFile) -> Self {
+        let (tab, error) = match file.tab {
+            Ok(inner) => (Some(PyTabState { inner }), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        Self {
+            path: file.path,
+            tab,
+            error,
+        }
+    }
+}
+
+/// A window and its tabs, in the order they are shown.
+#[pyclass(name = "SessionWindow", frozen, get_all)]
+#[derive(Clone)]
+struct PySessionWindow {
+    path: PathBuf,
+    window: PyWindowState,
+    tabs: Vec<PyTabStateFile>,
+}
+
+/// Every window in a Notepad session, plus the tabs no window lists.
+#[pyclass(name = "Session", frozen, get_all)]
+struct PySession {
+    windows: Vec<PySessionWindow>,
+    orphaned_tabs: Vec<PyTabStateFile>,
+}
+
+/// Parses a TabState file.
+#[pyfunction]
+fn parse_tabstate(data: &[u8]) -> PyResult<PyTabState> {
+    TabState::from_buffer(data)
+        .map(|inner| PyTabState { inner })
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Parses a WindowState file.
+#[pyfunction]
+fn parse_windowstate(data: &[u8]) -> PyResult<PyWindowState> {
+    WindowState::from_buffer(data)
+        .map(|inner| PyWindowState { inner })
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Loads every window and tab from the TabState and WindowState folders. Both default to the
+/// folders of the current user.
+#[pyfunction]
+#[pyo3(signature = (tabstate_dir=None, windowstate_dir=None))]
+fn load_session(
+    tabstate_dir: Option<PathBuf>,
+    windowstate_dir: Option<PathBuf>,
+) -> PyResult<PySession> {
+    let tabstate_dir = tabstate_dir
+        .or_else(get_tabstate_dir)
+        .ok_or_else(|| PyValueError::new_err("%localappdata% is not set. Pass tabstate_dir."))?;
+    let windowstate_dir = windowstate_dir.or_else(get_windowstate_dir);
+
+    let session = read_session(tabstate_dir, windowstate_dir.as_deref())?;
+    let windows = session
+        .windows
+        .into_iter()
+        .map(|w| PySessionWindow {
+            path: w.window.path,
+            window: PyWindowState {
+                inner: w.window.window,
+            },
+            tabs: w.tabs.into_iter().map(Into::into).collect(),
+        })
+        .collect();
+
+    Ok(PySession {
+        windows,
+        orphaned_tabs: session.orphaned_tabs.into_iter().map(Into::into).collect(),
+    })
+}
+
+fn py_bool(b: bool) -> &'static str {
+    if b {
+        "True"
+    } else {
+        "False"
+    }
+}
+
+#[pymodule]
+fn tabstate_util(_py: Python, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyOptions>()?;
+    m.add_class::<PyEditChunk>()?;
+    m.add_class::<PyTabState>()?;
+    m.add_class::<PyWindowState>()?;
+    m.add_class::<PyTabStateFile>()?;
+    m.add_class::<PySessionWindow>()?;
+    m.add_class::<PySession>()?;
+    m.add_function(wrap_pyfunction!(parse_tabstate, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_windowstate, m)?)?;
+    m.add_function(wrap_pyfunction!(load_session, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,59 @@
+from os import PathLike
+from typing import List, Optional, Tuple, Union
+
+class Options:
+    word_wrap: bool
+    right_to_left: bool
+    show_unicode_control: bool
+
+class EditChunk:
+    position: int
+    deletion_count: int
+    added_text: str
+    crc: Optional[int]
+
+class TabState:
+    is_saved: bool
+    path: Optional[str]
+    # Names like "UTF8" and "CRLF".
+    encoding: Optional[str]
+    carriage_type: Optional[str]
+    filetime: Optional[int]
+    content_hash: Optional[bytes]
+    cursor: Tuple[int, int]
+    options: Options
+    text: str
+    chunks: List[EditChunk]
+    crc: Optional[int]
+    def reconstruct_text(self) -> str: ...
+    def to_bytes(self) -> bytes: ...
+
+class WindowState:
+    sequence_number: int
+    tabs: List[str]
+    active_tab: int
+    top_left: Tuple[int, int]
+    bottom_right: Tuple[int, int]
+    window_size: Tuple[int, int]
+    crc: Optional[int]
+
+class TabStateFile:
+    path: str
+    tab: Optional[TabState]
+    error: Optional[str]
+
+class SessionWindow:
+    path: str
+    window: WindowState
+    tabs: List[TabStateFile]
+
+class Session:
+    windows: List[SessionWindow]
+    orphaned_tabs: List[TabStateFile]
+
+def parse_tabstate(data: bytes) -> TabState: ...
+def parse_windowstate(data: bytes) -> WindowState: ...
+def load_session(
+    tabstate_dir: Optional[Union[str, PathLike]] = None,
+    windowstate_dir: Optional[Union[str, PathLike]] = None,
+) -> Session: ...
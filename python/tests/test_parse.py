@@ -0,0 +1,92 @@
+import struct
+import zlib
+
+import pytest
+
+import tabstate_util
+
+
+def build_tab(state, text, chunks=()):
+    body = b"\x00" + state  # unk, then the state and metadata
+    body += b"\x02\x03"  # cursor start, cursor end
+    body += b"\x01\x00\x00\x01"  # options
+    body += bytes([len(text)]) + text.encode("utf-16-le")
+    body += b"\x00"
+    data = b"NP" + body + struct.pack(">I", zlib.crc32(body))
+    for position, deleted, added in chunks:
+        chunk = bytes([position, deleted, len(added)]) + added.encode("utf-16-le")
+        data += chunk + struct.pack(">I", zlib.crc32(chunk))
+    return data
+
+
+def unsaved_tab(text, chunks=()):
+    return build_tab(b"\x00", text, chunks)
+
+
+def saved_tab(text, path, encoding, carriage_type):
+    metadata = b"\x01"  # saved
+    metadata += bytes([len(path)]) + path.encode("utf-16-le")
+    metadata += bytes([len(text)])  # full buffer size
+    metadata += bytes([encoding, carriage_type])
+    metadata += b"\x00"  # filetime
+    metadata += bytes(32)  # content hash
+    metadata += b"\x00\x01"  # unk, unk2
+    return build_tab(metadata, text)
+
+
+def test_parse_tabstate():
+    tab = tabstate_util.parse_tabstate(unsaved_tab("Hello", [(5, 0, " World")]))
+    assert not tab.is_saved
+    assert tab.path is None
+    assert tab.encoding is None
+    assert tab.cursor == (2, 3)
+    assert tab.options.word_wrap
+    assert not tab.options.right_to_left
+    assert tab.text == "Hello"
+    assert [c.added_text for c in tab.chunks] == [" World"]
+    assert tab.reconstruct_text() == "Hello World"
+    assert tabstate_util.parse_tabstate(tab.to_bytes()).text == "Hello"
+
+
+def test_saved_tab_names():
+    tab = tabstate_util.parse_tabstate(saved_tab("Hello", "C:\\a.txt", 5, 3))
+    assert tab.is_saved
+    assert tab.path == "C:\\a.txt"
+    assert tab.encoding == "UTF8"
+    assert tab.carriage_type == "CRLF"
+    assert tab.content_hash == bytes(32)
+
+    tab = tabstate_util.parse_tabstate(saved_tab("Hello", "C:\\a.txt", 2, 1))
+    assert (tab.encoding, tab.carriage_type) == ("UTF16LE", "Unix")
+
+
+def test_parse_tabstate_error():
+    with pytest.raises(ValueError):
+        tabstate_util.parse_tabstate(b"NOPE")
+
+
+def test_parse_windowstate():
+    guid = bytes(range(16))
+    body = b"\x00" + b"\x01" + guid + b"\x00" + struct.pack("<6I", 1, 2, 3, 4, 5, 6) + b"\x00"
+    head = b"\x07" + bytes([len(body)])
+    data = b"NP" + head + body + struct.pack(">I", zlib.crc32(head + body))
+
+    window = tabstate_util.parse_windowstate(data)
+    assert window.sequence_number == 7
+    assert window.tabs == ["03020100-0504-0706-0809-0a0b0c0d0e0f"]
+    assert window.active_tab == 0
+    assert window.top_left == (1, 2)
+    assert window.window_size == (5, 6)
+
+
+def test_load_session(tmp_path):
+    guid = "03020100-0504-0706-0809-0a0b0c0d0e0f"
+    (tmp_path / f"{guid}.bin").write_bytes(unsaved_tab("Hi"))
+    (tmp_path / "broken.bin").write_bytes(b"NP")
+
+    session = tabstate_util.load_session(tmp_path, tmp_path / "missing")
+    assert session.windows == []
+    tabs = {str(f.path).rsplit("/", 1)[-1].rsplit("\\", 1)[-1]: f for f in session.orphaned_tabs}
+    assert tabs[f"{guid}.bin"].tab.text == "Hi"
+    assert tabs["broken.bin"].tab is None
+    assert tabs["broken.bin"].error
@@ -0,0 +1,226 @@
+#![doc = "Things in a TabState file that parse fine, but that we didn't expect. These used to be printed to stderr. Now they are collected while parsing, so callers can decide what to do with them."]
+
+use crate::reader::offset_of;
+use crate::refs::tabstate::TabStateRefs;
+use crate::refs::varint::VarIntRef;
+use crate::spans::bytes_of;
+use crate::varint::VarInt;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AnomalyKind {
+    /// Bytes after the last edit chunk that could not be read as a chunk. This is usually a chunk
+    /// Notepad was still writing.
+    TrailingBytes,
+    /// A field we don't know the meaning of had a value we haven't seen before.
+    UnexpectedValue,
+    /// A varint that uses more bytes than it needs to.
+    NonCanonicalVarInt,
+    /// A CRC that doesn't match the bytes it covers.
+    CrcMismatch,
+}
+
+/// Something unexpected in a TabState file. If you see one of these on a file Notepad wrote, please
+/// open an issue and send the file along, so I can take a look.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// Offset of the bytes in the file.
+    pub offset: usize,
+    pub len: usize,
+    /// The field the bytes belong to, named the same way as in `spans`.
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:08X} {} ({:?}): {}",
+            self.offset, self.field, self.kind, self.message
+        )
+    }
+}
+
+/// Goes through the parsed file and collects every anomaly, in file order.
+pub(crate) fn find_anomalies(refs: &TabStateRefs) -> Vec<Anomaly> {
+    let mut finder = AnomalyFinder {
+        buffer: refs.get_raw_buffer(),
+        anomalies: vec![],
+    };
+
+    if let Some(metadata) = refs.get_metadata() {
+        finder.check_varint(metadata.get_file_path_len(), "metadata.file_path.len");
+        finder.check_varint(metadata.get_full_buffer_size(), "metadata.full_buffer_size");
+        finder.check_varint(metadata.filetime, "metadata.filetime");
+        finder.check_value(metadata.unk, "metadata.unk", &[0]);
+    }
+
+    finder.check_varint(refs.get_cursor_start(), "cursor.start");
+    finder.check_varint(refs.get_cursor_end(), "cursor.end");
+
+    let option_bytes = bytes_of(refs.get_options());
+    let option_fields = [
+        "options.word_wrap",
+        "options.right_to_left",
+        "options.show_unicode_control",
+        "options.unk",
+    ];
+    for (byte, field) in option_bytes.iter().zip(option_fields) {
+        finder.check_value(byte, field, &[0, 1]);
+    }
+
+    finder.check_varint(refs.get_buffer_len(), "text.len");
+
+    let footer = refs.get_footer();
+    finder.check_value(&footer.the_number_zero, "footer.the_number_zero", &[0]);
+    finder.check_crc(
+        &footer.crc[..],
+        "footer.crc",
+        footer.get_crc(),
+        refs.calculate_crc(),
+    );
+
+    for (i, chunk) in refs.get_chunks().iter().enumerate() {
+        finder.check_varint(chunk.get_position(), &format!("chunks[{i}].position"));
+        finder.check_varint(
+            chunk.get_deletion_count(),
+            &format!("chunks[{i}].deletion_count"),
+        );
+        finder.check_varint(
+            chunk.get_addition_count(),
+            &format!("chunks[{i}].addition_count"),
+        );
+        finder.check_crc(
+            &chunk.get_crc_bytes()[..],
+            &format!("chunks[{i}].crc"),
+            chunk.get_crc(),
+            chunk.calculate_crc(),
+        );
+    }
+
+    let trailing = refs.get_trailing_bytes();
+    if !trailing.is_empty() {
+        finder.push(
+            trailing,
+            "trailing",
+            AnomalyKind::TrailingBytes,
+            format!("{} bytes after the last edit chunk", trailing.len()),
+        );
+    }
+
+    finder.anomalies
+}
+
+/// The anomaly for a varint that isn't in its shortest form, at `offset` in the file. See
+/// `varint::is_canonical` for why these are worth reporting.
+pub(crate) fn non_canonical_varint(offset: usize, varint: VarIntRef, field: &str) -> Anomaly {
+    let value = varint.decode_lossless();
+    Anomaly {
+        kind: AnomalyKind::NonCanonicalVarInt,
+        offset,
+        len: varint.size_of(),
+        field: field.to_string(),
+        message: format!(
+            "{value} takes {} bytes, but only needs {}",
+            varint.size_of(),
+            VarInt::new(value).size_of()
+        ),
+    }
+}
+
+struct AnomalyFinder<'b> {
+    buffer: &'b [u8],
+    anomalies: Vec<Anomaly>,
+}
+
+impl AnomalyFinder<'_> {
+    fn push(&mut self, bytes: &[u8], field: &str, kind: AnomalyKind, message: String) {
+        self.anomalies.push(Anomaly {
+            kind,
+            offset: offset_of(self.buffer, bytes),
+            len: bytes.len(),
+            field: field.to_string(),
+            message,
+        });
+    }
+    fn check_varint(&mut self, varint: VarIntRef, field: &str) {
+        if !varint.is_canonical() {
+            let offset = offset_of(self.buffer, varint.get_buffer());
+            self.anomalies
+                .push(non_canonical_varint(offset, varint, field));
+        }
+    }
+    fn check_value(&mut self, value: &u8, field: &str, expected: &[u8]) {
+        if !expected.contains(value) {
+            self.push(
+                core::slice::from_ref(value),
+                field,
+                AnomalyKind::UnexpectedValue,
+                format!("Expected one of {expected:?}, got {value}"),
+            );
+        }
+    }
+    fn check_crc(&mut self, bytes: &[u8], field: &str, stored: u32, calculated: u32) {
+        if stored != calculated {
+            self.push(
+                bytes,
+                field,
+                AnomalyKind::CrcMismatch,
+                format!("Stored {stored:08X}, calculated {calculated:08X}"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::anomaly::AnomalyKind;
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::test_util::unsaved_tab;
+
+    #[test]
+    fn clean_file_has_no_anomalies() {
+        let bytes = unsaved_tab().to_bytes();
+        let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+        assert!(refs.get_anomalies().is_empty());
+    }
+
+    #[test]
+    fn finds_anomalies() {
+        let mut tab = unsaved_tab();
+        tab.the_number_zero = 7;
+        tab.chunks[0].crc = Some(0xDEADBEEF);
+        let mut bytes = tab.to_bytes();
+        // Cursor start is at offset 4. Make it a two byte zero: 0x80 0x00.
+        bytes.splice(4..5, [0x80, 0x00]);
+        // And half a chunk at the end.
+        bytes.extend_from_slice(&[0x01, 0x00]);
+
+        let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+        let found = refs
+            .get_anomalies()
+            .iter()
+            .map(|a| (a.kind, a.field.as_str(), a.offset))
+            .collect::<Vec<_>>();
+        // Trailing bytes, then the chunk, then the footer.
+        let footer = bytes.len() - 2 - 9 - 5;
+        assert_eq!(
+            found,
+            [
+                (AnomalyKind::NonCanonicalVarInt, "cursor.start", 4),
+                (AnomalyKind::UnexpectedValue, "footer.the_number_zero", footer),
+                (AnomalyKind::CrcMismatch, "footer.crc", footer + 1),
+                (AnomalyKind::CrcMismatch, "chunks[0].crc", bytes.len() - 2 - 4),
+                (AnomalyKind::TrailingBytes, "trailing", bytes.len() - 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use clap::Args;
+use serde_json::json;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+use tabstate_util::redact::{redact, redact_unparsed, RedactOptions};
+use tabstate_util::refs::tabstate::TabStateRefs;
+use tabstate_util::spans::{get_field_spans_from_refs, skim_field_spans};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+#[derive(Args)]
+pub struct BugReportArgs {
+    /// The TabState file that didn't parse, or that printed warnings.
+    input: PathBuf,
+    /// Where to write the zip. Defaults to `tabstate-bug-report-{file name}.zip` in the current
+    /// directory.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Include the original file when it can't be parsed, since the redacted copy of those only keeps
+    /// the header and metadata. Only use this if you are fine with sharing everything in the tab.
+    #[arg(long)]
+    include_original: bool,
+}
+
+/// Writes a zip with everything needed to look into a file the parser doesn't understand:
+///
+/// - `report.json`: the crate version, the parse error or anomalies, and the header bytes.
+/// - `redacted.bin`: the file with the text and path replaced. If it couldn't be parsed, only the
+///   header, the metadata and the lengths are kept, and everything else is zeroed.
+/// - `fields.json`: the field span map of `redacted.bin`. Bytes no field covers only get an offset and
+///   a length.
+/// - `original.bin`: only with `--include-original`, when the file could not be parsed.
+pub fn run(args: BugReportArgs) -> std::io::Result<()> {
+    let buffer = std::fs::read(&args.input)?;
+    let file_name = args
+        .input
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("tabstate-bug-report-{file_name}.zip")));
+
+    let mut files = vec![];
+    let mut report = json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "file_name": file_name,
+        "file_size": buffer.len(),
+        // The magic and state byte. These don't contain anything from the tab.
+        "header": buffer.iter().take(4).map(|b| format!("{b:02X}")).collect::<String>(),
+    });
+
+    match TabStateRefs::from_buffer(&buffer[..]) {
+        Ok(refs) => {
+            report["anomalies"] = json!(refs.get_anomalies());
+
+            let redacted = redact(&buffer[..], RedactOptions { chunks: true })?;
+            let redacted_refs = TabStateRefs::from_buffer(&redacted[..])?;
+            let spans = get_field_spans_from_refs(&redacted_refs);
+            files.push(("fields.json", to_json(&spans)?));
+            files.push(("redacted.bin", redacted));
+        }
+        Err(e) => {
+            report["error"] = json!({
+                "kind": format!("{:?}", e.kind()),
+                "message": e.message(),
+            });
+
+            let redacted = redact_unparsed(&buffer[..]);
+            files.push(("fields.json", to_json(&skim_field_spans(&redacted[..]))?));
+            files.push(("redacted.bin", redacted));
+            if args.include_original {
+                files.push(("original.bin", buffer.clone()));
+            } else {
+                eprintln!(
+                    "The file could not be parsed, so the redacted copy only has the header and the \
+                    metadata. Use --include-original to add the whole file."
+                );
+            }
+        }
+    }
+    files.insert(0, ("report.json", to_json(&report)?));
+
+    write_zip(&output, &files)?;
+    println!("Wrote {}", output.display());
+    println!("Please attach it to a new GitHub issue.");
+
+    Ok(())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> std::io::Result<Vec<u8>> {
+    serde_json::to_vec_pretty(value).map_err(Error::other)
+}
+
+fn write_zip(path: &Path, files: &[(&str, Vec<u8>)]) -> std::io::Result<()> {
+    let mut zip = ZipWriter::new(std::fs::File::create(path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, contents) in files {
+        zip.start_file(*name, options).map_err(Error::other)?;
+        zip.write_all(contents)?;
+    }
+    zip.finish().map_err(Error::other)?;
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use clap::Args;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use tabstate_util::diff::{SkimDiff, TabDiff};
+use tabstate_util::refs::tabstate::TabStateRefs;
+use tabstate_util::tabstate::TabState;
+
+#[derive(Args)]
+pub struct CompareArgs {
+    /// The older TabState file.
+    old: PathBuf,
+    /// The newer TabState file.
+    new: PathBuf,
+    /// Print the diff as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: CompareArgs) -> std::io::Result<()> {
+    let old = std::fs::read(&args.old)?;
+    let new = std::fs::read(&args.new)?;
+
+    match (parse(&args.old, &old), parse(&args.new, &new)) {
+        (Some(old), Some(new)) => print(&TabDiff::new(&old, &new), args.json),
+        // Like the `.0.bin` and `.1.bin` files, which we can only compare by what can be skimmed.
+        _ => {
+            eprintln!("warning: only comparing the fields that can be found without parsing");
+            print(&SkimDiff::new(&old, &new), args.json)
+        }
+    }
+}
+
+fn print<D: serde::Serialize + std::fmt::Display>(diff: &D, json: bool) -> std::io::Result<()> {
+    if json {
+        let json = serde_json::to_string_pretty(diff).map_err(Error::other)?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    print!("{diff}");
+
+    Ok(())
+}
+
+/// Returns `None` if the file doesn't parse, after saying why.
+fn parse(path: &Path, buffer: &[u8]) -> Option<TabState> {
+    match TabStateRefs::from_buffer(buffer) {
+        Ok(refs) => {
+            crate::warn_anomalies(path, refs.get_anomalies());
+            Some(refs.to_tab_state())
+        }
+        Err(e) => {
+            eprintln!("warning: {}: {e}", path.display());
+            None
+        }
+    }
+}
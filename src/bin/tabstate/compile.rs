@@ -0,0 +1,26 @@
+use clap::Args;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use tabstate_util::tabstate::TabState;
+
+#[derive(Args)]
+pub struct CompileArgs {
+    /// JSON file in the same format `tabstate export` writes. Leave out `crc` or any of the `*_len`
+    /// and `full_buffer_size` fields to have them calculated.
+    input: PathBuf,
+    /// Where to write the binary TabState file.
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+pub fn run(args: CompileArgs) -> std::io::Result<()> {
+    let json = std::fs::read_to_string(&args.input)?;
+    let tab: TabState = serde_json::from_str(&json).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid TabState JSON: {e}"),
+        )
+    })?;
+
+    std::fs::write(&args.output, tab.to_bytes())
+}
@@ -0,0 +1,51 @@
+use clap::Args;
+use std::path::PathBuf;
+use tabstate_util::diff::DiskDiff;
+use tabstate_util::refs::tabstate::TabStateRefs;
+use tabstate_util::text::CodePage;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The binary TabState file.
+    tab: PathBuf,
+    /// The file the tab was saved to.
+    disk: PathBuf,
+    /// The code page for ANSI tabs, as a number (`1252`) or a name (`shift_jis`).
+    #[arg(long, value_parser = crate::export::parse_code_page, default_value = "1252")]
+    code_page: CodePage,
+    /// The amount of unchanged lines to show around each change.
+    #[arg(short = 'U', long, default_value_t = 3)]
+    context: usize,
+}
+
+pub fn run(args: DiffArgs) -> std::io::Result<()> {
+    let buffer = std::fs::read(&args.tab)?;
+    let refs = TabStateRefs::from_buffer(&buffer[..])?;
+    crate::warn_anomalies(&args.tab, refs.get_anomalies());
+    let tab = refs.to_tab_state();
+    let disk = std::fs::read(&args.disk)?;
+
+    let diff = DiskDiff::new(&tab, &disk, args.code_page);
+    let disk_name = args.disk.display().to_string();
+    if diff.had_decode_errors {
+        eprintln!("warning: {disk_name}: not valid in the tab's encoding, the diff may be off");
+    }
+    if diff.line_endings_differ {
+        eprintln!("note: {disk_name}: line breaks differ from the tab's carriage type");
+    }
+    match diff.hash_matches {
+        Some(true) => eprintln!("note: {disk_name}: matches the tab's content hash"),
+        Some(false) => eprintln!("note: {disk_name}: does not match the tab's content hash"),
+        None => {}
+    }
+    if diff.is_unchanged() {
+        eprintln!("note: no unsaved changes");
+    }
+
+    print!(
+        "{}",
+        diff.unified(&disk_name, &args.tab.display().to_string(), args.context)
+    );
+
+    Ok(())
+}
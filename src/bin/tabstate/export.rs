@@ -0,0 +1,86 @@
+use clap::{Args, ValueEnum};
+use std::io::{Error, Write};
+use std::path::PathBuf;
+use tabstate_util::refs::tabstate::TabStateRefs;
+use tabstate_util::text::{CodePage, LineEnding};
+
+#[derive(Copy, Clone, ValueEnum)]
+enum LineEndingArg {
+    Lf,
+    Crlf,
+    Cr,
+}
+
+impl From<LineEndingArg> for LineEnding {
+    fn from(arg: LineEndingArg) -> Self {
+        match arg {
+            LineEndingArg::Lf => LineEnding::LF,
+            LineEndingArg::Crlf => LineEnding::CRLF,
+            LineEndingArg::Cr => LineEnding::CR,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// The binary TabState file.
+    input: PathBuf,
+    /// Where to write the output. Prints to stdout if not provided.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Write the text with the edit chunks applied, instead of JSON.
+    #[arg(long, conflicts_with = "saved")]
+    text: bool,
+    /// Write the bytes Notepad would save the tab as, in its own encoding and line breaks.
+    #[arg(long)]
+    saved: bool,
+    /// The code page for `--saved` ANSI tabs, as a number (`1252`) or a name (`shift_jis`).
+    #[arg(long, value_parser = parse_code_page, default_value = "1252")]
+    code_page: CodePage,
+    /// The line breaks for `--text`. Defaults to the tab's own line breaks.
+    #[arg(long, value_enum)]
+    line_endings: Option<LineEndingArg>,
+}
+
+pub fn run(args: ExportArgs) -> std::io::Result<()> {
+    let buffer = std::fs::read(&args.input)?;
+    let refs = TabStateRefs::from_buffer(&buffer[..])?;
+    crate::warn_anomalies(&args.input, refs.get_anomalies());
+    let tab = refs.to_tab_state();
+    let line_endings = args.line_endings.map(LineEnding::from);
+    let output = if args.text {
+        tab.text_with_line_endings(line_endings.unwrap_or(tab.line_ending()))
+            .into_bytes()
+    } else if args.saved {
+        let saved = tab.to_saved_bytes(args.code_page);
+        for unmappable in &saved.unmappable {
+            eprintln!(
+                "warning: {}: {:?} at char {} is not in {}, written as '?'",
+                args.input.display(),
+                unmappable.c,
+                unmappable.offset,
+                args.code_page.name()
+            );
+        }
+        saved.bytes
+    } else {
+        serde_json::to_string_pretty(&tab)
+            .map_err(Error::other)?
+            .into_bytes()
+    };
+
+    match args.output {
+        Some(path) => std::fs::write(path, output),
+        // Text is printed as is, so it matches what would be written to a file.
+        None if args.text || args.saved => std::io::stdout().lock().write_all(&output),
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&output)?;
+            writeln!(stdout)
+        }
+    }
+}
+
+pub(crate) fn parse_code_page(label: &str) -> Result<CodePage, String> {
+    CodePage::from_label(label).ok_or_else(|| format!("unknown code page: {label}"))
+}
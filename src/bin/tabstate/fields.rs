@@ -0,0 +1,41 @@
+use clap::Args;
+use std::io::Error;
+use std::path::PathBuf;
+use tabstate_util::refs::tabstate::TabStateRefs;
+use tabstate_util::spans::{get_field_spans_from_refs, SpanKind};
+
+#[derive(Args)]
+pub struct FieldsArgs {
+    /// The binary TabState file.
+    input: PathBuf,
+    /// Print the spans as JSON instead of a table.
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: FieldsArgs) -> std::io::Result<()> {
+    let buffer = std::fs::read(&args.input)?;
+    let refs = TabStateRefs::from_buffer(&buffer[..])?;
+    crate::warn_anomalies(&args.input, refs.get_anomalies());
+    let spans = get_field_spans_from_refs(&refs);
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&spans).map_err(Error::other)?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    for span in spans {
+        let marker = match span.kind {
+            SpanKind::Field => " ",
+            SpanKind::Unknown => "?",
+            SpanKind::Uncovered => "!",
+        };
+        println!(
+            "{:08X} {:>6} {marker} {:<32} {}",
+            span.offset, span.len, span.field, span.value
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use clap::Args;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use tabstate_util::refs::tabstate::TabStateRefs;
+use tabstate_util::spans::{get_field_spans_from_refs, skim_field_spans, FieldSpan, SpanKind};
+
+const BYTES_PER_LINE: usize = 0x10;
+const RESET: &str = "\x1b[0m";
+/// Foreground colors that known fields cycle through.
+const FIELD_COLORS: [&str; 6] = [
+    "\x1b[36m", // cyan
+    "\x1b[32m", // green
+    "\x1b[35m", // magenta
+    "\x1b[34m", // blue
+    "\x1b[95m", // bright magenta
+    "\x1b[96m", // bright cyan
+];
+/// Black on yellow, for fields we parse but don't know the meaning of. None of the field colors
+/// are yellow, so these stand out.
+const UNKNOWN_COLOR: &str = "\x1b[30;43m";
+/// White on red, for bytes no field covers.
+const UNCOVERED_COLOR: &str = "\x1b[97;41m";
+
+#[derive(Args)]
+pub struct HexdumpArgs {
+    /// The binary TabState file.
+    input: PathBuf,
+    /// Don't print colors. Also disabled when the `NO_COLOR` environment variable is set.
+    #[arg(long)]
+    no_color: bool,
+}
+
+pub fn run(args: HexdumpArgs) -> std::io::Result<()> {
+    let buffer = std::fs::read(&args.input)?;
+    // Files we can't parse are the ones most worth looking at, so they get whatever fields can be
+    // skimmed, and the rest is uncovered.
+    let spans = match TabStateRefs::from_buffer(&buffer[..]) {
+        Ok(refs) => {
+            crate::warn_anomalies(&args.input, refs.get_anomalies());
+            get_field_spans_from_refs(&refs)
+        }
+        Err(e) => {
+            eprintln!(
+                "warning: {}: {e}, so only the fields that could be skimmed are labeled",
+                args.input.display()
+            );
+            skim_field_spans(&buffer[..])
+        }
+    };
+    let color = !args.no_color && std::env::var_os("NO_COLOR").is_none();
+
+    print!("{}", hexdump(&buffer[..], &spans[..], color));
+    Ok(())
+}
+
+fn span_color(index: usize, span: &FieldSpan) -> &'static str {
+    match span.kind {
+        SpanKind::Field => FIELD_COLORS[index % FIELD_COLORS.len()],
+        SpanKind::Unknown => UNKNOWN_COLOR,
+        SpanKind::Uncovered => UNCOVERED_COLOR,
+    }
+}
+
+/// Renders the buffer as a hexdump with each span in its own color, followed by a legend. Without
+/// color, unknown and uncovered bytes are marked with `?` and `!` in the ascii column instead.
+fn hexdump(buffer: &[u8], spans: &[FieldSpan], color: bool) -> String {
+    // The span index for every byte, so each line can look up its colors.
+    let mut owners = vec![0; buffer.len()];
+    for (i, span) in spans.iter().enumerate() {
+        owners[span.offset..span.end()].fill(i);
+    }
+
+    let mut out = String::new();
+    for (line, bytes) in buffer.chunks(BYTES_PER_LINE).enumerate() {
+        let start = line * BYTES_PER_LINE;
+        let _ = write!(out, "{start:08X}  ");
+
+        let mut ascii = String::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            let owner = owners[start + i];
+            let span = &spans[owner];
+            let c = match byte {
+                0x20..=0x7E => *byte as char,
+                _ => '.',
+            };
+
+            if color {
+                let code = span_color(owner, span);
+                let _ = write!(out, "{code}{byte:02X}{RESET} ");
+                let _ = write!(ascii, "{code}{c}{RESET}");
+            } else {
+                let _ = write!(out, "{byte:02X} ");
+                ascii.push(match span.kind {
+                    SpanKind::Field => c,
+                    SpanKind::Unknown => '?',
+                    SpanKind::Uncovered => '!',
+                });
+            }
+        }
+
+        let padding = (BYTES_PER_LINE - bytes.len()) * 3;
+        let _ = writeln!(out, "{:padding$} {ascii}", "");
+    }
+
+    out.push('\n');
+    for (i, span) in spans.iter().enumerate() {
+        let (code, reset) = match color {
+            true => (span_color(i, span), RESET),
+            false => ("", ""),
+        };
+        let marker = match span.kind {
+            SpanKind::Field => ' ',
+            SpanKind::Unknown => '?',
+            SpanKind::Uncovered => '!',
+        };
+        let _ = writeln!(
+            out,
+            "{code}{marker} {:08X} +{:<6}{reset} {:<32} {}",
+            span.offset, span.len, span.field, span.value
+        );
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hexdump::{hexdump, span_color, FIELD_COLORS, UNCOVERED_COLOR, UNKNOWN_COLOR};
+    use tabstate_util::spans::{skim_field_spans, FieldSpan, SpanKind};
+
+    fn span(offset: usize, len: usize, kind: SpanKind) -> FieldSpan {
+        FieldSpan {
+            offset,
+            len,
+            field: format!("field{offset}"),
+            value: String::new(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn colors_by_kind() {
+        for color in FIELD_COLORS {
+            assert_ne!(color, UNKNOWN_COLOR);
+            assert_ne!(color, UNCOVERED_COLOR);
+            // Yellow and red are what the unknown and uncovered backgrounds are made of.
+            assert!(
+                !color.contains("33m") && !color.contains("31m"),
+                "{color:?}"
+            );
+        }
+
+        let field = span(0, 1, SpanKind::Field);
+        assert_eq!(span_color(0, &field), FIELD_COLORS[0]);
+        assert_eq!(span_color(1, &field), FIELD_COLORS[1]);
+        assert_eq!(span_color(FIELD_COLORS.len(), &field), FIELD_COLORS[0]);
+        assert_eq!(span_color(0, &span(0, 1, SpanKind::Unknown)), UNKNOWN_COLOR);
+        assert_eq!(
+            span_color(0, &span(0, 1, SpanKind::Uncovered)),
+            UNCOVERED_COLOR
+        );
+    }
+
+    #[test]
+    fn colors_each_byte_by_its_span() {
+        let buffer = b"NP\x00\x01";
+        let spans = [
+            span(0, 2, SpanKind::Field),
+            span(2, 1, SpanKind::Unknown),
+            span(3, 1, SpanKind::Uncovered),
+        ];
+
+        let out = hexdump(buffer, &spans, true);
+        let line = out.lines().next().unwrap();
+        let reset = "\x1b[0m";
+        let expected = format!(
+            "{field}4E{reset} {field}50{reset} {UNKNOWN_COLOR}00{reset} {UNCOVERED_COLOR}01{reset} ",
+            field = FIELD_COLORS[0]
+        );
+        assert!(
+            line.starts_with(&format!("00000000  {expected}")),
+            "{line:?}"
+        );
+
+        let out = hexdump(buffer, &spans, false);
+        assert!(out.lines().next().unwrap().ends_with(" NP?!"));
+    }
+
+    /// A file that doesn't parse still gets the header labeled, and the rest is uncovered.
+    #[test]
+    fn unparsed_file_is_skimmed() {
+        let buffer = b"NP\x00\x07garbage";
+        let out = hexdump(buffer, &skim_field_spans(buffer), false);
+        assert!(out.lines().next().unwrap().ends_with(" NP?.!!!!!!!"));
+        assert!(out.contains("  00000003 +1      header.state"));
+        assert!(out.contains("! 00000004 +7      uncovered"));
+    }
+}
@@ -0,0 +1,96 @@
+mod bug_report;
+mod compare;
+mod compile;
+mod diff;
+mod export;
+mod fields;
+mod hexdump;
+mod redact;
+mod stats;
+mod timeline;
+mod verify;
+#[cfg(feature = "watch")]
+mod watch;
+
+use clap::{Parser, Subcommand};
+use std::path::Path;
+use tabstate_util::anomaly::Anomaly;
+
+/// Utilities for Windows Notepad TabState files.
+#[derive(Parser)]
+#[command(name = "tabstate", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Bundle a redacted copy of a file, its field map and any anomalies into a zip to attach to an
+    /// issue.
+    BugReport(bug_report::BugReportArgs),
+    /// Compare two TabState files for the same tab field by field, like two snapshots.
+    Compare(compare::CompareArgs),
+    /// Compile a JSON TabState back into a binary TabState file.
+    Compile(compile::CompileArgs),
+    /// Print a unified diff from the file on disk to the tab's text, showing what wasn't saved.
+    Diff(diff::DiffArgs),
+    /// Export a binary TabState file as JSON.
+    Export(export::ExportArgs),
+    /// Print the byte span and value of every field in a TabState file.
+    Fields(fields::FieldsArgs),
+    /// Print a colored hexdump of a TabState file, with a legend for each field.
+    Hexdump(hexdump::HexdumpArgs),
+    /// Replace the text and path in a TabState file with placeholders, so it can be shared.
+    Redact(redact::RedactArgs),
+    /// Count the values of the unknown fields across a directory of TabState files, and what the
+    /// known fields looked like alongside them.
+    Stats(stats::StatsArgs),
+    /// Write a forensic timeline for a TabState file or directory.
+    Timeline(timeline::TimelineArgs),
+    /// Check a TabState file or directory for signs that something other than Notepad edited it.
+    Verify(verify::VerifyArgs),
+    /// Watch a TabState directory and print what changed in each tab.
+    #[cfg(feature = "watch")]
+    Watch(watch::WatchArgs),
+}
+
+/// Prints anomalies to stderr, so they don't end up in the output of commands that write to stdout.
+fn warn_anomalies(path: &Path, anomalies: &[Anomaly]) {
+    for anomaly in anomalies {
+        eprintln!("warning: {}: {anomaly}", path.display());
+    }
+}
+
+/// Says how many `.0.bin` and `.1.bin` files in a TabState folder were left out, since we can't parse
+/// them yet.
+fn warn_skipped(dir: &Path) {
+    let Ok(skipped) = tabstate_util::dir::get_skipped_paths(dir) else {
+        return;
+    };
+    if !skipped.is_empty() {
+        eprintln!(
+            "warning: {}: skipped {} .0.bin/.1.bin files, their layout isn't supported yet",
+            dir.display(),
+            skipped.len()
+        );
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    match Cli::parse().command {
+        Command::BugReport(args) => bug_report::run(args),
+        Command::Compare(args) => compare::run(args),
+        Command::Compile(args) => compile::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Export(args) => export::run(args),
+        Command::Fields(args) => fields::run(args),
+        Command::Hexdump(args) => hexdump::run(args),
+        Command::Redact(args) => redact::run(args),
+        Command::Stats(args) => stats::run(args),
+        Command::Timeline(args) => timeline::run(args),
+        Command::Verify(args) => verify::run(args),
+        #[cfg(feature = "watch")]
+        Command::Watch(args) => watch::run(args),
+    }
+}
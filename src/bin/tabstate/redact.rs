@@ -0,0 +1,27 @@
+use clap::Args;
+use std::path::PathBuf;
+use tabstate_util::redact::{redact, RedactOptions};
+
+#[derive(Args)]
+pub struct RedactArgs {
+    /// TabState file to redact.
+    input: PathBuf,
+    /// Where to write the redacted file.
+    #[arg(short, long)]
+    output: PathBuf,
+    /// Also redact the text added by each edit chunk.
+    #[arg(long)]
+    chunks: bool,
+}
+
+pub fn run(args: RedactArgs) -> std::io::Result<()> {
+    let buffer = std::fs::read(&args.input)?;
+    let redacted = redact(
+        &buffer,
+        RedactOptions {
+            chunks: args.chunks,
+        },
+    )?;
+
+    std::fs::write(&args.output, redacted)
+}
@@ -0,0 +1,69 @@
+use clap::Args;
+use std::collections::BTreeMap;
+use std::io::Error;
+use std::path::PathBuf;
+use tabstate_util::dir::read_tabstate_dir;
+use tabstate_util::stats::{FieldStats, UnknownStats};
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// A directory of TabState files.
+    input: PathBuf,
+    /// Print the stats as JSON instead of a report.
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: StatsArgs) -> std::io::Result<()> {
+    crate::warn_skipped(&args.input);
+    let mut stats = UnknownStats::new();
+    for file in read_tabstate_dir(&args.input)? {
+        match &file.tab {
+            Ok(tab) => stats.add(tab),
+            Err(e) => {
+                eprintln!("warning: {}: {e}", file.path.display());
+                match std::fs::read(&file.path) {
+                    Ok(buffer) => stats.add_unparsed(&buffer),
+                    Err(_) => stats.add_failed(),
+                }
+            }
+        }
+    }
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&stats).map_err(Error::other)?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    println!(
+        "{} files parsed, {} could not be parsed",
+        stats.files, stats.failed
+    );
+    print_fields(&stats.fields);
+    if !stats.unparsed.is_empty() {
+        println!();
+        println!("In the files that could not be parsed:");
+        print_fields(&stats.unparsed);
+    }
+
+    Ok(())
+}
+
+fn print_fields(fields: &BTreeMap<&'static str, FieldStats>) {
+    for (field, field_stats) in fields {
+        println!();
+        println!("{field}");
+        for (value, value_stats) in &field_stats.values {
+            println!("  {value:#04X}: {} files", value_stats.count);
+            for (property, counts) in &value_stats.properties {
+                let counts = counts
+                    .iter()
+                    .map(|(v, n)| format!("{v} {n}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                println!("    {property:<30} {counts}");
+            }
+        }
+    }
+}
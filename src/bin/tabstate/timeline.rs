@@ -0,0 +1,47 @@
+use clap::{Args, ValueEnum};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use tabstate_util::dir::{read_tabstate_dir, TabStateFile};
+use tabstate_util::timeline::{get_timeline, write_bodyfile, write_jsonl};
+
+#[derive(Copy, Clone, ValueEnum)]
+enum TimelineFormat {
+    /// mactime bodyfile
+    Bodyfile,
+    /// Timesketch compatible JSON lines
+    Jsonl,
+}
+
+#[derive(Args)]
+pub struct TimelineArgs {
+    /// A TabState file, or a directory of TabState files.
+    input: PathBuf,
+    #[arg(short, long, value_enum, default_value_t = TimelineFormat::Bodyfile)]
+    format: TimelineFormat,
+    /// Where to write the timeline. Prints to stdout if not provided.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: TimelineArgs) -> std::io::Result<()> {
+    let files = if args.input.is_dir() {
+        crate::warn_skipped(&args.input);
+        read_tabstate_dir(&args.input)?
+    } else {
+        vec![TabStateFile::read(&args.input)?]
+    };
+    let events = get_timeline(&files[..]);
+
+    let mut writer: Box<dyn Write> = match args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    match args.format {
+        TimelineFormat::Bodyfile => write_bodyfile(&mut writer, &events[..])?,
+        TimelineFormat::Jsonl => write_jsonl(&mut writer, &events[..])?,
+    }
+
+    writer.flush()
+}
@@ -0,0 +1,73 @@
+use clap::Args;
+use std::io::Error;
+use std::path::PathBuf;
+use tabstate_util::dir::read_windowstate_dir;
+use tabstate_util::verify::{verify_dir, verify_file, VerifyReport};
+
+#[derive(Args)]
+pub struct VerifyArgs {
+    /// A TabState file, or a directory of TabState files.
+    input: PathBuf,
+    /// The WindowState folder to check that each tab is listed in. Defaults to the `WindowState`
+    /// folder next to the TabState folder, if there is one.
+    #[arg(long)]
+    windowstate: Option<PathBuf>,
+    /// Print the reports as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: VerifyArgs) -> std::io::Result<()> {
+    let tabstate_dir = match args.input.is_dir() {
+        true => Some(args.input.as_path()),
+        false => args.input.parent(),
+    };
+    let windowstate_dir = args.windowstate.or_else(|| {
+        tabstate_dir
+            .and_then(|dir| dir.parent())
+            .map(|dir| dir.join("WindowState"))
+            .filter(|dir| dir.is_dir())
+    });
+
+    let reports = if args.input.is_dir() {
+        crate::warn_skipped(&args.input);
+        verify_dir(&args.input, windowstate_dir.as_deref())?
+    } else {
+        let windows = match &windowstate_dir {
+            Some(dir) => Some(read_windowstate_dir(dir)?),
+            None => None,
+        };
+        vec![verify_file(&args.input, windows.as_deref())?]
+    };
+
+    if args.json {
+        let json = serde_json::to_string_pretty(&reports).map_err(Error::other)?;
+        println!("{json}");
+        return Ok(());
+    }
+
+    if windowstate_dir.is_none() {
+        eprintln!("warning: no WindowState folder found, so tabs were not checked against it");
+    }
+    for report in &reports {
+        print_report(report);
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &VerifyReport) {
+    println!("{}: {:?}", report.path.display(), report.verdict);
+    for evidence in &report.evidence {
+        let offset = evidence
+            .offset
+            .map_or(String::new(), |offset| format!(" @ {offset:#010X}"));
+        let field = evidence.field.as_deref().unwrap_or("-");
+        println!(
+            "  {:<10} {:<15} {field}{offset}: {}",
+            format!("{:?}", evidence.severity),
+            format!("{:?}", evidence.check),
+            evidence.message
+        );
+    }
+}
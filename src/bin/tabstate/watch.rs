@@ -0,0 +1,157 @@
+use clap::Args;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::channel;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tabstate_util::diff::text_change;
+use tabstate_util::dir::{get_tabstate_paths, is_tabstate_file};
+use tabstate_util::filetime::format_iso8601;
+use tabstate_util::refs::tabstate::TabStateRefs;
+use tabstate_util::tabstate::TabState;
+use tabstate_util::text::LineEnding;
+
+#[derive(Args)]
+pub struct WatchArgs {
+    /// The TabState directory to watch.
+    dir: PathBuf,
+    /// How many times to re-read a file that fails its CRC check before giving up on that change.
+    #[arg(long, default_value_t = 20)]
+    retries: usize,
+    /// Milliseconds to wait between re-reads.
+    #[arg(long, default_value_t = 50)]
+    retry_delay: u64,
+}
+
+pub fn run(args: WatchArgs) -> std::io::Result<()> {
+    let retry_delay = Duration::from_millis(args.retry_delay);
+
+    let mut tabs = HashMap::new();
+    crate::warn_skipped(&args.dir);
+    for path in get_tabstate_paths(&args.dir)? {
+        if let Ok(Some(tab)) = read_settled(&path, args.retries, retry_delay) {
+            tabs.insert(path, tab);
+        }
+    }
+    println!(
+        "Watching {} ({} tabs). Press Ctrl+C to stop.",
+        args.dir.display(),
+        tabs.len()
+    );
+
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(Error::other)?;
+    watcher
+        .watch(&args.dir, RecursiveMode::NonRecursive)
+        .map_err(Error::other)?;
+
+    for event in rx {
+        let event = event.map_err(Error::other)?;
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            continue;
+        }
+
+        for path in event.paths.iter().filter(|p| is_tabstate_file(p)) {
+            match read_settled(path, args.retries, retry_delay) {
+                Ok(Some(tab)) => {
+                    print_change(path, tabs.get(path), &tab);
+                    tabs.insert(path.clone(), tab);
+                }
+                Ok(None) => {
+                    if tabs.remove(path).is_some() {
+                        println!("[{}] {} closed", now(), display_name(path));
+                    }
+                }
+                Err(e) => eprintln!("[{}] {}: {e}", now(), display_name(path)),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the file until it parses and every CRC checks out, since Notepad might still be writing it.
+/// Returns `None` if the file was deleted.
+fn read_settled(path: &Path, retries: usize, delay: Duration) -> std::io::Result<Option<TabState>> {
+    for _ in 0..=retries {
+        let buffer = match std::fs::read(path) {
+            Ok(buffer) => buffer,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        if let Ok(refs) = TabStateRefs::from_buffer(&buffer[..]) {
+            if refs.is_crc_valid() {
+                return Ok(Some(refs.to_tab_state()));
+            }
+        }
+
+        std::thread::sleep(delay);
+    }
+
+    Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("CRC still did not match after {retries} retries. Skipping this change."),
+    ))
+}
+
+fn print_change(path: &Path, old: Option<&TabState>, new: &TabState) {
+    let file_path = new
+        .metadata
+        .as_ref()
+        .map_or("Unsaved tab", |m| m.path.as_str());
+    let old = match old {
+        Some(old) if old == new => return,
+        Some(old) => old,
+        None => {
+            println!("[{}] {} opened ({file_path})", now(), display_name(path));
+            return;
+        }
+    };
+    println!("[{}] {} changed ({file_path})", now(), display_name(path));
+
+    // Notepad appends chunks while the tab is open, and folds them back into the text buffer when it
+    // rewrites the file. If the old chunks aren't a prefix of the new ones, the file was rewritten.
+    if new.text == old.text && new.chunks.starts_with(&old.chunks[..]) {
+        for (i, chunk) in new.chunks.iter().enumerate().skip(old.chunks.len()) {
+            println!(
+                "  + chunk #{i} at char {}: deleted {}, added {:?}",
+                chunk.position, chunk.deletion_count, chunk.added_text
+            );
+        }
+    } else {
+        println!(
+            "  rewritten: {} chunks folded into the text buffer",
+            old.chunks.len()
+        );
+    }
+
+    // LF keeps one char per line break, so the positions still line up with the chunks.
+    let old_text = old.text_with_line_endings(LineEnding::LF);
+    let new_text = new.text_with_line_endings(LineEnding::LF);
+    if old_text != new_text {
+        let (position, removed, inserted) = text_change(&old_text, &new_text);
+        println!(
+            "  text at char {position}: removed {removed:?}, inserted {inserted:?} ({} -> {} chars)",
+            old_text.chars().count(),
+            new_text.chars().count()
+        );
+    }
+}
+
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+fn now() -> String {
+    let micros = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as i64);
+    format_iso8601(micros)
+}
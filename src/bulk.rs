@@ -0,0 +1,154 @@
+#![doc = "Parses large collections of TabState files in parallel. Files are memory mapped and parsed with `TabStateRefs`, so the only allocations per file are whatever the caller makes from the refs. Read text with the `get_*_chars` accessors, which borrow from the map whenever the text is aligned."]
+
+use crate::refs::tabstate::TabStateRefs;
+use crate::tabstate::TabState;
+use crate::TabStateReader;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::fs::File;
+use std::path::Path;
+
+/// How many files are parsed in parallel before the results are handed out.
+pub const DEFAULT_BATCH_SIZE: usize = 0x400;
+
+/// An iterator that parses files in parallel, one batch at a time, and returns the results in the
+/// same order as the paths.
+pub struct BulkParser<'p, P, T, F> {
+    paths: &'p [P],
+    map: F,
+    batch_size: usize,
+    next: usize,
+    batch: std::vec::IntoIter<(&'p Path, T)>,
+}
+
+impl<'p, P, T, F> BulkParser<'p, P, T, F>
+where
+    P: AsRef<Path> + Sync,
+    T: Send,
+    F: Fn(&Path, std::io::Result<TabStateRefs>) -> T + Sync,
+{
+    /// Calls `map` with the parsed refs of each file. The refs point into the memory mapped file, so
+    /// they can't outlive the call. Copy out whatever you need.
+    pub fn new(paths: &'p [P], map: F) -> Self {
+        Self {
+            paths,
+            map,
+            batch_size: DEFAULT_BATCH_SIZE,
+            next: 0,
+            batch: vec![].into_iter(),
+        }
+    }
+    /// Sets how many files are parsed before results are handed out. Larger batches keep more threads
+    /// busy, but hold more results in memory.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+}
+
+impl<'p, P, T, F> Iterator for BulkParser<'p, P, T, F>
+where
+    P: AsRef<Path> + Sync,
+    T: Send,
+    F: Fn(&Path, std::io::Result<TabStateRefs>) -> T + Sync,
+{
+    type Item = (&'p Path, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(result) = self.batch.next() {
+            return Some(result);
+        }
+        if self.next >= self.paths.len() {
+            return None;
+        }
+
+        let end = (self.next + self.batch_size).min(self.paths.len());
+        let map = &self.map;
+        // `collect` on an indexed parallel iterator keeps the order of the paths.
+        let batch = self.paths[self.next..end]
+            .par_iter()
+            .map(|path| {
+                let path = path.as_ref();
+                (path, map_file(path, map))
+            })
+            .collect::<Vec<_>>();
+
+        self.next = end;
+        self.batch = batch.into_iter();
+        self.batch.next()
+    }
+}
+
+/// Memory maps the file and passes the parsed refs to `map`.
+fn map_file<T, F>(path: &Path, map: &F) -> T
+where
+    F: Fn(&Path, std::io::Result<TabStateRefs>) -> T,
+{
+    // The map is only valid as long as nobody truncates the file while we are parsing it. TabState
+    // files are small and we are reading them after the fact, so this is fine for evidence, but don't
+    // point this at a live Notepad folder.
+    let mmap = File::open(path).and_then(|file| unsafe { Mmap::map(&file) });
+    let mmap = match mmap {
+        Ok(mmap) => mmap,
+        Err(e) => return map(path, Err(e)),
+    };
+
+    let refs = TabStateReader::new(&mmap[..])
+        .and_then(|reader| reader.get_refs())
+        .map_err(Into::into);
+    map(path, refs)
+}
+
+/// Parses every file into an owned `TabState`, in parallel. Results come back in the same order as the
+/// paths.
+pub fn parse_files<P: AsRef<Path> + Sync>(
+    paths: &[P],
+) -> impl Iterator<Item = (&Path, std::io::Result<TabState>)> {
+    BulkParser::new(paths, |_, refs| refs.map(|r| r.to_tab_state()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bulk::{parse_files, BulkParser};
+    use crate::tabstate::TabState;
+    use crate::test_util::unsaved_tab;
+
+    #[test]
+    fn results_are_in_order() {
+        let dir = std::env::temp_dir().join(format!("tabstate-bulk-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut paths = vec![];
+        for i in 0..10 {
+            let tab = TabState {
+                text: format!("File {i}"),
+                ..unsaved_tab()
+            };
+            let path = dir.join(format!("{i}.bin"));
+            std::fs::write(&path, tab.to_bytes()).unwrap();
+            paths.push(path);
+        }
+        // A file that doesn't exist should come back as an error in its place.
+        paths.insert(5, dir.join("missing.bin"));
+
+        let results = parse_files(&paths[..]).collect::<Vec<_>>();
+        assert_eq!(results.len(), paths.len());
+        assert!(results[5].1.is_err());
+        for (i, (path, tab)) in results.iter().filter(|(_, t)| t.is_ok()).enumerate() {
+            assert_eq!(path.file_stem().unwrap(), i.to_string().as_str());
+            assert_eq!(tab.as_ref().unwrap().text, format!("File {i}"));
+        }
+
+        // Small batches should give the same order.
+        let lens = BulkParser::new(&paths[..], |_, refs| {
+            refs.map(|r| r.get_buffer_chars().len()).ok()
+        })
+        .batch_size(3)
+        .map(|(_, len)| len)
+        .collect::<Vec<_>>();
+        assert_eq!(lens[5], None);
+        assert_eq!(lens.iter().flatten().count(), 10);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
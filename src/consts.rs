@@ -14,3 +14,9 @@ pub const CARRIAGE_TYPES: [u8; 2] = [CarriageType::Unix as u8, CarriageType::CRL
 pub const METADATA_UNK2: u8 = 0x01;
 pub const MAX_VAL: u8 = 0x7F;
 pub const SIGN_BIT: u8 = 0x80;
+/// The longest varint that can hold a `u128`, 7 bits at a time. Anything longer is rejected before it
+/// is decoded.
+pub const MAX_VARINT_LEN: usize = 0x13;
+pub const MAGIC: [u8; 3] = *b"NP\0";
+/// The footer CRC covers everything after the "NP" in the magic, up to the CRC itself.
+pub const CRC_START: usize = 0x2;
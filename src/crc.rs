@@ -0,0 +1,44 @@
+//! CRC32 used by the TabState footer. This is the regular IEEE CRC32 (the one zip uses), so nothing
+//! fancy here, but I didn't want to pull in a whole crate for a table and a loop.
+
+const POLYNOMIAL: u32 = 0xEDB88320;
+
+const TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Calculates the CRC32 of the provided buffer.
+pub fn crc32(buffer: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in buffer {
+        crc = TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crc::crc32;
+
+    /// The standard check value for CRC32.
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+}
@@ -0,0 +1,568 @@
+#![doc = "Diffs for tabs. `DiskDiff` diffs the text of a tab against the file it was saved to, to show what was changed in Notepad but never saved. `TabDiff` compares two TabState files for the same tab field by field, like two snapshots taken at different times. `SkimDiff` compares files that don't parse, like the `.0.bin` and `.1.bin` files next to a tab, by the fields that can be skimmed."]
+
+use crate::enums::Encoding;
+use crate::filetime::{filetime_to_unix_micros, format_iso8601};
+use crate::spans::{skim_field_spans, to_hex, SpanKind};
+use crate::tabstate::chunk::EditChunk;
+use crate::tabstate::TabState;
+use crate::text::{convert_line_endings, decode_text, hash_bytes, CodePage, LineEnding};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+/// The file on disk and the tab's text, decoded and ready to diff. Both texts have LF line breaks,
+/// so the diff is only about the text itself. Whether the line breaks match is in
+/// `line_endings_differ`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DiskDiff {
+    /// The file on disk, decoded with the tab's encoding.
+    pub disk_text: String,
+    /// The tab's text, with the edit chunks applied.
+    pub tab_text: String,
+    /// Whether the file had bytes that aren't valid in the tab's encoding. If it does, the file was
+    /// probably saved with another encoding, or another code page for ANSI files.
+    pub had_decode_errors: bool,
+    /// Whether the file's line breaks aren't all the ones the tab's carriage type says.
+    pub line_endings_differ: bool,
+    /// Whether the SHA-256 of the file matches `Metadata::content_hash`, meaning the file is still the
+    /// one Notepad last loaded or saved. `None` for unsaved tabs.
+    pub hash_matches: Option<bool>,
+}
+
+impl DiskDiff {
+    /// Decodes `disk` with the tab's encoding and reconstructs the tab's text. Unsaved tabs are
+    /// decoded as UTF-8, Notepad's default. `code_page` is only used for ANSI files.
+    pub fn new(tab: &TabState, disk: &[u8], code_page: CodePage) -> Self {
+        let encoding = tab.metadata.as_ref().map_or(Encoding::UTF8, |m| m.encoding);
+        let decoded = decode_text(disk, encoding, code_page);
+        let line_endings_differ =
+            convert_line_endings(&decoded.text, tab.line_ending()) != decoded.text;
+        let hash_matches = tab
+            .metadata
+            .as_ref()
+            .map(|m| hash_bytes(disk) == m.content_hash);
+
+        Self {
+            disk_text: convert_line_endings(&decoded.text, LineEnding::LF),
+            tab_text: tab.text_with_line_endings(LineEnding::LF),
+            had_decode_errors: decoded.had_errors,
+            line_endings_differ,
+            hash_matches,
+        }
+    }
+    /// Whether the tab has the same text as the file, ignoring line breaks.
+    pub fn is_unchanged(&self) -> bool {
+        self.disk_text == self.tab_text
+    }
+    /// A unified diff from the file on disk to the tab, with `context` lines around each change.
+    /// Lines starting with `+` are in the tab but not on disk, so they're the unsaved changes.
+    #[cfg(feature = "diff")]
+    pub fn unified(&self, disk_name: &str, tab_name: &str, context: usize) -> String {
+        similar::TextDiff::from_lines(&self.disk_text, &self.tab_text)
+            .unified_diff()
+            .context_radius(context)
+            .header(disk_name, tab_name)
+            .to_string()
+    }
+}
+
+/// One field that has a different value in each file. Values are formatted for reading, and `none`
+/// means the file doesn't have the field, like the metadata of unsaved tabs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldChange {
+    /// The field, named the same way as in `spans`.
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// The part of a text that changed, found by trimming what's the same at the start and the end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TextChange {
+    /// Offset in UTF-16 chars where the change starts, same as the cursor and edit chunks.
+    pub position: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+impl TextChange {
+    /// Returns `None` if the texts are the same.
+    pub fn new(old: &str, new: &str) -> Option<Self> {
+        if old == new {
+            return None;
+        }
+
+        let (position, removed, inserted) = text_change(old, new);
+        Some(Self {
+            position,
+            removed: removed.to_string(),
+            inserted: inserted.to_string(),
+        })
+    }
+}
+
+/// Finds the part of the text that changed by trimming the common prefix and suffix. Returns the
+/// position in UTF-16 chars, and the removed and inserted text.
+pub fn text_change<'t>(old: &'t str, new: &'t str) -> (usize, &'t str, &'t str) {
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new.len()), |((i, _), _)| i);
+    // Make sure the prefix ends on a char boundary in both strings.
+    let prefix = (0..=prefix)
+        .rev()
+        .find(|i| old.is_char_boundary(*i) && new.is_char_boundary(*i))
+        .unwrap_or(0);
+
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .chars()
+        .rev()
+        .zip(new_rest.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum::<usize>();
+
+    (
+        old[..prefix].encode_utf16().count(),
+        &old_rest[..old_rest.len() - suffix],
+        &new_rest[..new_rest.len() - suffix],
+    )
+}
+
+/// What changed between two versions of a tab.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TabDiff {
+    /// Every field outside of the text and chunks that changed, in the order they are in the file.
+    pub fields: Vec<FieldChange>,
+    /// How the text buffer changed. `None` if it didn't.
+    pub text: Option<TextChange>,
+    /// How the text changed with the edit chunks applied, which is what Notepad shows.
+    pub reconstructed_text: Option<TextChange>,
+    /// Whether the new file doesn't start with the old file's chunks. Notepad folds the chunks into
+    /// the text buffer when it rewrites the file, so this usually means it was rewritten.
+    pub rewritten: bool,
+    /// The chunks in the new file that the old one doesn't have. If the file was rewritten, this is
+    /// every chunk in the new file.
+    pub added_chunks: Vec<EditChunk>,
+}
+
+impl TabDiff {
+    pub fn new(old: &TabState, new: &TabState) -> Self {
+        let old_fields = get_fields(old);
+        let new_fields = get_fields(new);
+        let fields = old_fields
+            .into_iter()
+            .zip(new_fields)
+            .filter(|((_, old), (_, new))| old != new)
+            .map(|((field, old), (_, new))| FieldChange {
+                field: field.to_string(),
+                old,
+                new,
+            })
+            .collect();
+
+        let rewritten = !new.chunks.starts_with(&old.chunks[..]);
+        let added_chunks = match rewritten {
+            true => new.chunks.clone(),
+            false => new.chunks[old.chunks.len()..].to_vec(),
+        };
+
+        Self {
+            fields,
+            text: TextChange::new(&old.text, &new.text),
+            reconstructed_text: TextChange::new(&old.reconstruct_text(), &new.reconstruct_text()),
+            rewritten,
+            added_chunks,
+        }
+    }
+    /// Whether the two files have the same values. CRCs and lengths are not compared, since they
+    /// follow from the rest.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+            && self.text.is_none()
+            && self.reconstructed_text.is_none()
+            && !self.rewritten
+            && self.added_chunks.is_empty()
+    }
+}
+
+impl Display for TabDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+
+        for change in &self.fields {
+            writeln!(f, "{}: {} -> {}", change.field, change.old, change.new)?;
+        }
+        for (name, change) in [
+            ("text buffer", &self.text),
+            ("reconstructed text", &self.reconstructed_text),
+        ] {
+            if let Some(change) = change {
+                writeln!(
+                    f,
+                    "{name} at char {}: removed {:?}, inserted {:?}",
+                    change.position, change.removed, change.inserted
+                )?;
+            }
+        }
+        if self.rewritten {
+            writeln!(f, "rewritten: the old chunks are not in the new file")?;
+        }
+        for chunk in &self.added_chunks {
+            writeln!(
+                f,
+                "+ chunk at char {}: deleted {}, added {:?}",
+                chunk.position, chunk.deletion_count, chunk.added_text
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What changed between two files that `TabDiff` can't compare, because they don't parse. That's the
+/// `.0.bin` and `.1.bin` files next to a tab, and tabs with an unknown version or field value. Only
+/// the fields `skim_field_spans` finds are compared, which for the `.0.bin` and `.1.bin` files is the
+/// header and the state byte. The bytes no field covers are compared as hex, as `uncovered`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SkimDiff {
+    /// Every skimmed field that changed, in the order they are in the old file, then the fields only
+    /// the new file has. A field only one of the files has is `none` in the other.
+    pub fields: Vec<FieldChange>,
+}
+
+impl SkimDiff {
+    pub fn new(old: &[u8], new: &[u8]) -> Self {
+        let old_fields = get_skimmed_fields(old);
+        let new_fields = get_skimmed_fields(new);
+        let value = |fields: &[(String, String)], field: &str| {
+            fields
+                .iter()
+                .find(|(f, _)| f == field)
+                .map_or("none".to_string(), |(_, value)| value.clone())
+        };
+
+        let mut fields = Vec::new();
+        for (field, old) in &old_fields {
+            let new = value(&new_fields, field);
+            if *old != new {
+                fields.push(FieldChange {
+                    field: field.clone(),
+                    old: old.clone(),
+                    new,
+                });
+            }
+        }
+        for (field, new) in &new_fields {
+            if !old_fields.iter().any(|(f, _)| f == field) {
+                fields.push(FieldChange {
+                    field: field.clone(),
+                    old: "none".to_string(),
+                    new: new.clone(),
+                });
+            }
+        }
+
+        Self { fields }
+    }
+    /// Whether every skimmed field and the uncovered bytes are the same.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl Display for SkimDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+
+        for change in &self.fields {
+            writeln!(f, "{}: {} -> {}", change.field, change.old, change.new)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The value of each skimmed field. Uncovered spans don't have a value, so they get their bytes in hex.
+fn get_skimmed_fields(buffer: &[u8]) -> Vec<(String, String)> {
+    skim_field_spans(buffer)
+        .into_iter()
+        .map(|span| match span.kind {
+            SpanKind::Uncovered => {
+                let value = to_hex(&buffer[span.offset..span.end()]);
+                (span.field, value)
+            }
+            _ => (span.field, span.value),
+        })
+        .collect()
+}
+
+/// The value of each compared field, formatted for reading.
+fn get_fields(tab: &TabState) -> Vec<(&'static str, String)> {
+    let metadata = |f: &dyn Fn(&crate::tabstate::metadata::Metadata) -> String| {
+        tab.metadata.as_ref().map_or("none".to_string(), f)
+    };
+    let options = tab.options.as_bytes();
+
+    alloc::vec![
+        (
+            "header.magic",
+            match tab.version() {
+                Some(version) => format!("{version:?}"),
+                None => format!("{:#04X}", tab.header_unk),
+            },
+        ),
+        ("header.state", format!("{:?}", tab.get_state())),
+        ("metadata.file_path", metadata(&|m| format!("{:?}", m.path))),
+        (
+            "metadata.full_buffer_size",
+            metadata(&|m| {
+                m.full_buffer_size
+                    .unwrap_or_else(|| m.calculate_full_buffer_size(&tab.text))
+                    .to_string()
+            }),
+        ),
+        (
+            "metadata.encoding",
+            metadata(&|m| format!("{:?}", m.encoding))
+        ),
+        (
+            "metadata.carriage_type",
+            metadata(&|m| format!("{:?}", m.carriage_type)),
+        ),
+        (
+            "metadata.filetime",
+            metadata(&|m| match i64::try_from(m.filetime) {
+                Ok(_) => format_iso8601(filetime_to_unix_micros(m.filetime)),
+                Err(_) => format!("{:#X} (not a valid FILETIME)", m.filetime),
+            }),
+        ),
+        (
+            "metadata.content_hash",
+            metadata(&|m| m.content_hash.iter().map(|b| format!("{b:02X}")).collect()),
+        ),
+        ("metadata.unk", metadata(&|m| format!("{:#04X}", m.unk))),
+        ("metadata.unk2", metadata(&|m| format!("{:#04X}", m.unk2))),
+        ("cursor.start", tab.cursor_start.to_string()),
+        ("cursor.end", tab.cursor_end.to_string()),
+        ("options.word_wrap", tab.options.word_wrap().to_string()),
+        (
+            "options.right_to_left",
+            tab.options.right_to_left().to_string()
+        ),
+        (
+            "options.show_unicode_control",
+            tab.options.show_unicode_control().to_string(),
+        ),
+        ("options.unk", format!("{:#04X}", options[3])),
+        (
+            "footer.the_number_zero",
+            format!("{:#04X}", tab.the_number_zero)
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{text_change, DiskDiff, FieldChange, SkimDiff, TabDiff, TextChange};
+    use crate::enums::CarriageType;
+    use crate::tabstate::chunk::EditChunk;
+    use crate::tabstate::TabState;
+    use crate::test_util::saved_tab;
+    use crate::text::CodePage;
+
+    const DISK: &[u8] = b"one\r\ntwo\r\nthree\r\n";
+
+    fn tab() -> TabState {
+        let mut tab = TabState {
+            cursor_start: 0,
+            cursor_end: 0,
+            text: "one\rtwo\rthree\r".to_string(),
+            ..saved_tab()
+        };
+        // The text is `DISK` with CRLF line breaks, so this is the hash of `DISK`.
+        let metadata = tab.metadata.as_mut().unwrap();
+        metadata.content_hash = metadata.calculate_content_hash(&tab.text, CodePage::default());
+        tab
+    }
+
+    #[test]
+    fn unchanged_tab() {
+        let diff = DiskDiff::new(&tab(), DISK, CodePage::default());
+        assert!(diff.is_unchanged());
+        assert!(!diff.line_endings_differ);
+        assert!(!diff.had_decode_errors);
+        assert_eq!(diff.hash_matches, Some(true));
+        #[cfg(feature = "diff")]
+        assert_eq!(diff.unified("disk", "tab", 3), "");
+    }
+
+    #[test]
+    fn diffs_unsaved_edits() {
+        let mut tab = tab();
+        // Replace "two" with "2".
+        tab.chunks.push(EditChunk {
+            position: 4,
+            deletion_count: 3,
+            addition_count: None,
+            added_text: "2".to_string(),
+            crc: None,
+        });
+
+        let diff = DiskDiff::new(&tab, DISK, CodePage::default());
+        assert!(!diff.is_unchanged());
+        assert_eq!(diff.hash_matches, Some(true));
+        #[cfg(feature = "diff")]
+        assert_eq!(
+            diff.unified("list.txt", "tab.bin", 1),
+            "--- list.txt\n+++ tab.bin\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n"
+        );
+    }
+
+    #[test]
+    fn reports_line_endings_and_hash() {
+        let disk = b"one\ntwo\nthree\n";
+        let diff = DiskDiff::new(&tab(), disk, CodePage::default());
+        // Same text, but the file isn't the one Notepad saw, and it uses other line breaks.
+        assert!(diff.is_unchanged());
+        assert!(diff.line_endings_differ);
+        assert_eq!(diff.hash_matches, Some(false));
+    }
+
+    #[test]
+    fn finds_changed_text() {
+        assert_eq!(
+            text_change("Hello World", "Hello There World"),
+            (6, "", "There ")
+        );
+        assert_eq!(text_change("abc", "abc"), (3, "", ""));
+        assert_eq!(text_change("aéb", "aüb"), (1, "é", "ü"));
+        // Positions are in UTF-16 chars, like the cursor.
+        assert_eq!(text_change("😀a", "😀b"), (2, "a", "b"));
+    }
+
+    #[test]
+    fn compares_impossible_filetimes() {
+        let old = tab();
+        let mut new = old.clone();
+        new.metadata.as_mut().unwrap().filetime = u64::MAX;
+
+        let diff = TabDiff::new(&old, &new);
+        assert_eq!(diff.fields.len(), 1);
+        assert_eq!(diff.fields[0].field, "metadata.filetime");
+        assert_eq!(
+            diff.fields[0].new,
+            "0xFFFFFFFFFFFFFFFF (not a valid FILETIME)"
+        );
+    }
+
+    #[test]
+    fn compares_tab_pair_files() {
+        // Like a `.0.bin` and `.1.bin`, which have a state byte of their own.
+        let old = b"NP\x00\x02\x01\x02";
+        let new = b"NP\x00\x03\x01\x02\x03";
+        assert!(SkimDiff::new(old, old).is_empty());
+        assert_eq!(SkimDiff::new(old, old).to_string(), "no changes\n");
+
+        let diff = SkimDiff::new(old, new);
+        assert_eq!(
+            diff.to_string(),
+            "header.state: 2 -> 3\n\
+            uncovered: 0102 -> 010203\n"
+        );
+        // Fields the old file doesn't have are `none`.
+        let diff = SkimDiff::new(b"NP\x00", old);
+        assert_eq!(
+            diff.fields,
+            [
+                FieldChange {
+                    field: "header.state".to_string(),
+                    old: "none".to_string(),
+                    new: "2".to_string(),
+                },
+                FieldChange {
+                    field: "uncovered".to_string(),
+                    old: "none".to_string(),
+                    new: "0102".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn compares_fields_and_chunks() {
+        let old = tab();
+        assert!(TabDiff::new(&old, &old).is_empty());
+        assert_eq!(TabDiff::new(&old, &old).to_string(), "no changes\n");
+
+        let mut new = old.clone();
+        new.cursor_start = 5;
+        new.cursor_end = 5;
+        new.metadata.as_mut().unwrap().carriage_type = CarriageType::Unix;
+        new.chunks.push(EditChunk {
+            position: 4,
+            deletion_count: 3,
+            addition_count: None,
+            added_text: "2".to_string(),
+            crc: None,
+        });
+
+        let diff = TabDiff::new(&old, &new);
+        let change = |field: &str, old: &str, new: &str| FieldChange {
+            field: field.to_string(),
+            old: old.to_string(),
+            new: new.to_string(),
+        };
+        assert_eq!(
+            diff.fields,
+            [
+                change("metadata.full_buffer_size", "17", "14"),
+                change("metadata.carriage_type", "CRLF", "Unix"),
+                change("cursor.start", "0", "5"),
+                change("cursor.end", "0", "5"),
+            ]
+        );
+        assert_eq!(diff.text, None);
+        assert_eq!(
+            diff.reconstructed_text,
+            Some(TextChange {
+                position: 4,
+                removed: "two".to_string(),
+                inserted: "2".to_string(),
+            })
+        );
+        assert!(!diff.rewritten);
+        assert_eq!(diff.added_chunks, new.chunks);
+        assert_eq!(
+            diff.to_string(),
+            "metadata.full_buffer_size: 17 -> 14\n\
+            metadata.carriage_type: CRLF -> Unix\n\
+            cursor.start: 0 -> 5\n\
+            cursor.end: 0 -> 5\n\
+            reconstructed text at char 4: removed \"two\", inserted \"2\"\n\
+            + chunk at char 4: deleted 3, added \"2\"\n"
+        );
+
+        // Folding the chunk into the text buffer is a rewrite.
+        let mut folded = new.clone();
+        folded.text = new.reconstruct_text();
+        folded.chunks.clear();
+        let diff = TabDiff::new(&new, &folded);
+        assert!(diff.rewritten);
+        assert!(diff.added_chunks.is_empty());
+        assert_eq!(diff.reconstructed_text, None);
+        assert_eq!(diff.text.unwrap().inserted, "2");
+    }
+}
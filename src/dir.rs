@@ -0,0 +1,306 @@
+#![doc = "Helpers for finding and reading every TabState file in a directory."]
+
+use crate::tabstate::TabState;
+use crate::windowstate::{Guid, WindowState};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+/// The TabState folder, relative to `%localappdata%`.
+pub const TABSTATE_DIR: &str =
+    r"Packages\Microsoft.WindowsNotepad_8wekyb3d8bbwe\LocalState\TabState";
+
+/// The WindowState folder, relative to `%localappdata%`.
+pub const WINDOWSTATE_DIR: &str =
+    r"Packages\Microsoft.WindowsNotepad_8wekyb3d8bbwe\LocalState\WindowState";
+
+/// A TabState file read from disk, along with the file system metadata for the file.
+pub struct TabStateFile {
+    pub path: PathBuf,
+    pub fs_metadata: Metadata,
+    /// The parsed file. Files that fail to parse are still returned, so the caller can decide what to
+    /// do with them.
+    pub tab: std::io::Result<TabState>,
+}
+
+impl TabStateFile {
+    /// Reads and parses the file at the provided path.
+    pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let fs_metadata = std::fs::metadata(path)?;
+        let buffer = std::fs::read(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            fs_metadata,
+            tab: TabState::from_buffer(&buffer[..]).map_err(Into::into),
+        })
+    }
+}
+
+/// Returns the TabState folder for the current user, if `%localappdata%` is set.
+pub fn get_tabstate_dir() -> Option<PathBuf> {
+    let local_app_data = std::env::var_os("localappdata")?;
+    Some(Path::new(&local_app_data).join(TABSTATE_DIR))
+}
+
+/// Returns the WindowState folder for the current user, if `%localappdata%` is set.
+pub fn get_windowstate_dir() -> Option<PathBuf> {
+    let local_app_data = std::env::var_os("localappdata")?;
+    Some(Path::new(&local_app_data).join(WINDOWSTATE_DIR))
+}
+
+/// TabState files are named `{guid}.bin`. Tabs can also have a `{guid}.0.bin` and `{guid}.1.bin` in the
+/// same folder, which hold more state for the tab in a layout we can't parse yet. Anything with a
+/// second extension is skipped, see `is_tab_pair_file` and `get_skipped_paths`.
+pub fn is_tabstate_file(path: &Path) -> bool {
+    let is_bin = path.extension().is_some_and(|ext| ext == "bin");
+    let has_second_ext = path
+        .file_stem()
+        .is_some_and(|stem| Path::new(stem).extension().is_some());
+
+    is_bin && !has_second_ext
+}
+
+/// Whether the path is one of the `{guid}.0.bin` and `{guid}.1.bin` files next to a tab. They start
+/// with "NP" too, but their state byte isn't one `TabStateRefs` knows, so they fail to parse.
+pub fn is_tab_pair_file(path: &Path) -> bool {
+    let is_bin = path.extension().is_some_and(|ext| ext == "bin");
+    let second_ext = path
+        .file_stem()
+        .and_then(|stem| Path::new(stem).extension().map(|ext| ext.to_os_string()));
+
+    is_bin && second_ext.is_some_and(|ext| ext == "0" || ext == "1")
+}
+
+/// Gets the path of every `.0.bin` and `.1.bin` file in the provided directory, sorted. These are the
+/// files `get_tabstate_paths` leaves out, so callers can say what they skipped.
+pub fn get_skipped_paths<P: AsRef<Path>>(dir: P) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_tab_pair_file(&path) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Gets the path of every TabState file in the provided directory, sorted, so the order is the same
+/// every time. The `.0.bin` and `.1.bin` files are left out, see `get_skipped_paths`.
+pub fn get_tabstate_paths<P: AsRef<Path>>(dir: P) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && is_tabstate_file(&path) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+/// Reads every TabState file in the provided directory, in the same order as `get_tabstate_paths`.
+pub fn read_tabstate_dir<P: AsRef<Path>>(dir: P) -> std::io::Result<Vec<TabStateFile>> {
+    get_tabstate_paths(dir)?
+        .into_iter()
+        .map(TabStateFile::read)
+        .collect()
+}
+
+/// A WindowState file read from disk.
+pub struct WindowStateFile {
+    pub path: PathBuf,
+    pub fs_metadata: Metadata,
+    pub window: WindowState,
+    pub is_crc_valid: bool,
+}
+
+impl WindowStateFile {
+    /// Reads and parses the file at the provided path.
+    pub fn read<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let fs_metadata = std::fs::metadata(path)?;
+        let buffer = std::fs::read(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            fs_metadata,
+            window: WindowState::from_buffer(&buffer[..])?,
+            is_crc_valid: WindowState::is_crc_valid(&buffer[..]),
+        })
+    }
+}
+
+/// Reads the WindowState files in the provided directory. Notepad keeps two files per window, so
+/// only the newest one with a valid CRC is returned for each window, sorted by path. Files that fail
+/// to parse are skipped.
+pub fn read_windowstate_dir<P: AsRef<Path>>(dir: P) -> std::io::Result<Vec<WindowStateFile>> {
+    let mut windows: HashMap<String, WindowStateFile> = HashMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "bin") {
+            continue;
+        }
+        // `{guid}.0.bin` and `{guid}.1.bin` belong to the same window.
+        let Some(window_id) = path
+            .file_stem()
+            .and_then(|stem| Path::new(stem).file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+        else {
+            continue;
+        };
+        let Ok(file) = WindowStateFile::read(&path) else {
+            continue;
+        };
+        if !file.is_crc_valid {
+            continue;
+        }
+
+        match windows.get(&window_id) {
+            Some(newest) if newest.window.sequence_number >= file.window.sequence_number => {}
+            _ => {
+                windows.insert(window_id, file);
+            }
+        }
+    }
+
+    let mut windows = windows.into_values().collect::<Vec<_>>();
+    windows.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(windows)
+}
+
+/// A window and the TabState files for its tabs, in the order they are shown.
+pub struct SessionWindow {
+    pub window: WindowStateFile,
+    /// Tabs that the WindowState lists, but that have no TabState file, are left out.
+    pub tabs: Vec<TabStateFile>,
+}
+
+/// Every window in a Notepad session, along with any TabState files no window claims.
+pub struct Session {
+    pub windows: Vec<SessionWindow>,
+    pub orphaned_tabs: Vec<TabStateFile>,
+}
+
+/// Reads the TabState and WindowState folders and matches each tab to the window it belongs to. If
+/// `windowstate_dir` is `None`, or doesn't exist, every tab ends up in `orphaned_tabs`.
+pub fn read_session<P: AsRef<Path>>(
+    tabstate_dir: P,
+    windowstate_dir: Option<&Path>,
+) -> std::io::Result<Session> {
+    let mut tabs = HashMap::new();
+    let mut orphaned_tabs = vec![];
+    for file in read_tabstate_dir(tabstate_dir)? {
+        let guid = file
+            .path
+            .file_stem()
+            .and_then(|stem| Guid::parse(&stem.to_string_lossy()));
+        match guid {
+            Some(guid) => {
+                tabs.insert(guid, file);
+            }
+            None => orphaned_tabs.push(file),
+        }
+    }
+
+    let window_files = match windowstate_dir {
+        Some(dir) if dir.is_dir() => read_windowstate_dir(dir)?,
+        _ => vec![],
+    };
+
+    let mut windows = vec![];
+    for window in window_files {
+        let tabs = window
+            .window
+            .tabs
+            .iter()
+            .filter_map(|guid| tabs.remove(guid))
+            .collect();
+        windows.push(SessionWindow { window, tabs });
+    }
+
+    orphaned_tabs.extend(tabs.into_values());
+    orphaned_tabs.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(Session {
+        windows,
+        orphaned_tabs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dir::{is_tab_pair_file, is_tabstate_file, read_session};
+    use crate::tabstate::TabState;
+    use crate::test_util::unsaved_tab;
+    use crate::windowstate::{Guid, WindowState};
+    use std::path::Path;
+
+    #[test]
+    fn skips_tab_pair_files() {
+        let tab = Path::new("0c07e304-0604-4438-941d-0977da045fd9.bin");
+        let pair = Path::new("0c07e304-0604-4438-941d-0977da045fd9.0.bin");
+        assert!(is_tabstate_file(tab));
+        assert!(!is_tabstate_file(pair));
+        assert!(!is_tab_pair_file(tab));
+        assert!(is_tab_pair_file(pair));
+        assert!(is_tab_pair_file(Path::new(
+            "0c07e304-0604-4438-941d-0977da045fd9.1.bin"
+        )));
+        assert!(!is_tabstate_file(Path::new("notes.txt")));
+    }
+
+    /// Tabs should come back in the order the newest WindowState lists them.
+    #[test]
+    fn session_matches_tabs_to_windows() {
+        let dir = std::env::temp_dir().join(format!("tabstate-session-{}", std::process::id()));
+        let tab_dir = dir.join("TabState");
+        let window_dir = dir.join("WindowState");
+        std::fs::create_dir_all(&tab_dir).unwrap();
+        std::fs::create_dir_all(&window_dir).unwrap();
+
+        let guids = (1..=3u8).map(|i| Guid([i; 0x10])).collect::<Vec<_>>();
+        for (i, guid) in guids.iter().enumerate() {
+            let tab = TabState {
+                text: format!("Tab {i}"),
+                ..unsaved_tab()
+            };
+            std::fs::write(tab_dir.join(format!("{guid}.bin")), tab.to_bytes()).unwrap();
+        }
+
+        let mut window = WindowState {
+            sequence_number: 1,
+            bytes_to_crc: None,
+            unk: 0,
+            tabs: vec![guids[0]],
+            active_tab: 0,
+            top_left: [0, 0],
+            bottom_right: [800, 600],
+            window_size: [800, 600],
+            unk2: 0,
+            crc: None,
+            slack: vec![],
+        };
+        std::fs::write(window_dir.join("window.0.bin"), window.to_bytes()).unwrap();
+        window.sequence_number = 2;
+        window.tabs = vec![guids[2], guids[0]];
+        std::fs::write(window_dir.join("window.1.bin"), window.to_bytes()).unwrap();
+
+        let session = read_session(&tab_dir, Some(&window_dir)).unwrap();
+        assert_eq!(session.windows.len(), 1);
+        let texts = session.windows[0]
+            .tabs
+            .iter()
+            .map(|t| t.tab.as_ref().unwrap().text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["Tab 2", "Tab 0"]);
+        assert_eq!(session.orphaned_tabs.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
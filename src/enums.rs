@@ -1,8 +1,9 @@
 use bytemuck::{AnyBitPattern, Zeroable};
-use std::fmt::{Display, Formatter, UpperHex};
+use core::fmt::{Display, Formatter, UpperHex};
 
 #[repr(u8)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Encoding {
     ANSI = 1,
     UTF16LE = 2,
@@ -14,21 +15,32 @@ impl Encoding {
     pub fn as_value(&self) -> u8 {
         *self as u8
     }
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Encoding::ANSI),
+            2 => Some(Encoding::UTF16LE),
+            3 => Some(Encoding::UTF16BE),
+            4 => Some(Encoding::UTF8BOM),
+            5 => Some(Encoding::UTF8),
+            _ => None,
+        }
+    }
 }
 impl Display for Encoding {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.as_value())
     }
 }
 impl UpperHex for Encoding {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.as_value())
     }
 }
 unsafe impl Zeroable for Encoding {}
 unsafe impl AnyBitPattern for Encoding {}
 #[repr(u8)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CarriageType {
     Unix = 1,
     CRLF = 3,
@@ -37,14 +49,21 @@ impl CarriageType {
     pub fn as_value(&self) -> u8 {
         *self as u8
     }
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(CarriageType::Unix),
+            3 => Some(CarriageType::CRLF),
+            _ => None,
+        }
+    }
 }
 impl Display for CarriageType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.as_value())
     }
 }
 impl UpperHex for CarriageType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "{}", self.as_value())
     }
 }
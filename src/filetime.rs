@@ -0,0 +1,69 @@
+#![doc = "Helpers for the Windows FILETIME timestamps in TabState files."]
+
+use alloc::format;
+use alloc::string::String;
+
+/// FILETIME is 100ns intervals since 1601-01-01. This is the amount of intervals between that and
+/// the unix epoch.
+const FILETIME_UNIX_EPOCH: i128 = 116_444_736_000_000_000;
+
+/// Converts a FILETIME to microseconds since the unix epoch. The math is done in `i128`, since the
+/// file can have any `u64`. Divided by 10, every `u64` fits in an `i64`, so this never fails.
+pub fn filetime_to_unix_micros(filetime: u64) -> i64 {
+    ((filetime as i128 - FILETIME_UNIX_EPOCH) / 10) as i64
+}
+
+/// Formats microseconds since the unix epoch as an ISO 8601 UTC date.
+pub fn format_iso8601(timestamp: i64) -> String {
+    let secs = timestamp.div_euclid(1_000_000);
+    let micros = timestamp.rem_euclid(1_000_000);
+    let days = secs.div_euclid(86_400);
+    let secs_of_day = secs.rem_euclid(86_400);
+
+    // Civil from days, from Howard Hinnant's date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{micros:06}+00:00",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::filetime::{filetime_to_unix_micros, format_iso8601};
+
+    #[test]
+    fn filetime_conversion() {
+        // 2024-01-01T00:00:00Z
+        let micros = filetime_to_unix_micros(133_485_408_000_000_000);
+        assert_eq!(micros, 1_704_067_200_000_000);
+        assert_eq!(format_iso8601(micros), "2024-01-01T00:00:00.000000+00:00");
+    }
+
+    #[test]
+    fn filetime_with_high_bit() {
+        // These used to overflow when cast to i64.
+        for filetime in [1 << 63, (1 << 63) + 116_444_736_000_000_000 - 1, u64::MAX] {
+            let micros = filetime_to_unix_micros(filetime);
+            assert!(micros > 0);
+            format_iso8601(micros);
+        }
+        assert_eq!(filetime_to_unix_micros(0), -11_644_473_600_000_000);
+    }
+
+    #[test]
+    fn iso8601_before_epoch() {
+        assert_eq!(format_iso8601(-1), "1969-12-31T23:59:59.999999+00:00");
+    }
+}
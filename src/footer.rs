@@ -7,5 +7,12 @@ pub struct TabStateFooter {
     pub the_number_zero: u8,
     pub crc: [u8; 4],
 }
+impl TabStateFooter {
+    /// The CRC is stored big endian, unlike everything else in the file.
+    pub fn get_crc(&self) -> u32 {
+        u32::from_be_bytes(self.crc)
+    }
+}
+
 pub const FOOTER_SIZE: usize = 0x5;
-const _: () = assert!(std::mem::size_of::<TabStateFooter>() == FOOTER_SIZE);
+const _: () = assert!(core::mem::size_of::<TabStateFooter>() == FOOTER_SIZE);
@@ -1,7 +1,8 @@
 use bytemuck::{AnyBitPattern, Zeroable};
 
 #[repr(u8)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum State {
     Unsaved = 0,
     Saved = 1,
@@ -17,5 +18,16 @@ pub struct Header {
     pub(crate) state: State,
 }
 
+impl Header {
+    /// The magic bytes at the start of the file. The first two bytes should always be "NP".
+    pub fn get_magic(&self) -> &[u8; 3] {
+        &self.magic
+    }
+    /// The state of the file. This is the raw byte, so it might not be a valid `State`.
+    pub fn get_state(&self) -> u8 {
+        self.state as u8
+    }
+}
+
 pub const HEADER_SIZE: usize = 0x4;
-const _: () = assert!(std::mem::size_of::<Header>() == HEADER_SIZE);
+const _: () = assert!(core::mem::size_of::<Header>() == HEADER_SIZE);
@@ -1,14 +1,44 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+pub mod anomaly;
+#[cfg(feature = "rayon")]
+pub mod bulk;
 pub mod consts;
+pub mod crc;
+pub mod diff;
+#[cfg(feature = "std")]
+pub mod dir;
 pub mod enums;
+pub mod error;
+pub mod filetime;
 pub mod footer;
 pub mod header;
 pub mod options;
+pub mod reader;
+pub mod redact;
 pub mod refs;
+pub mod spans;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod tabstate;
+#[cfg(any(test, feature = "test-util"))]
+#[doc(hidden)]
+pub mod test_util;
+pub mod text;
+#[cfg(feature = "std")]
+pub mod timeline;
 pub mod varint;
+pub mod verify;
+pub mod version;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod windowstate;
 
 use crate::consts::*;
+use crate::error::{Error, ErrorKind};
 use crate::refs::tabstate::TabStateRefs;
-use std::io::{Error, ErrorKind};
 
 /// A structure that parses the Notepad buffer data.
 pub struct TabStateReader<'a> {
@@ -17,7 +47,7 @@ pub struct TabStateReader<'a> {
 
 impl<'a> TabStateReader<'a> {
     /// Returns a new `TabStateReader` that contains the provided buffer.
-    pub fn new(buffer: &'a [u8]) -> std::io::Result<Self> {
+    pub fn new(buffer: &'a [u8]) -> crate::error::Result<Self> {
         if buffer.is_empty() {
             return Err(Error::new(ErrorKind::InvalidData, "Buffer is empty."));
         }
@@ -26,7 +56,7 @@ impl<'a> TabStateReader<'a> {
     }
     /// Get references to the individual parts of the Notepad buffer, like the filepath and the text
     /// buffer, as well as some unknown metadata.
-    pub fn get_refs(&self) -> std::io::Result<TabStateRefs<'a>> {
+    pub fn get_refs(&self) -> crate::error::Result<TabStateRefs<'a>> {
         TabStateRefs::from_buffer(self.buffer)
     }
 }
@@ -37,7 +67,7 @@ impl<'a> TabStateReader<'a> {
 /// in order?
 #[allow(unused)]
 #[deprecated]
-fn read_cursed_size_format(size_buffer: &[u8]) -> std::io::Result<usize> {
+fn read_cursed_size_format(size_buffer: &[u8]) -> crate::error::Result<usize> {
     if size_buffer.len() > 2 {
         return Err(Error::new(
             ErrorKind::Unsupported,
@@ -64,10 +94,11 @@ fn read_cursed_size_format(size_buffer: &[u8]) -> std::io::Result<usize> {
     Ok(size)
 }
 
-#[cfg(test)]
+// These read the TabState files of the local Notepad install, so they only build on Windows.
+#[cfg(all(test, windows))]
 mod tests {
+    use crate::error::ErrorKind;
     use crate::TabStateReader;
-    use std::io::ErrorKind;
 
     const BUFFER_PATH: &str = concat!(
         env!("localappdata"),
@@ -2,7 +2,8 @@ use bytemuck::AnyBitPattern;
 
 /// An option struct that holds text editor option state for the tab.
 #[repr(C)]
-#[derive(Copy, Clone, AnyBitPattern)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, AnyBitPattern)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TabStateOptions {
     word_wrap: u8,
     right_to_left: u8,
@@ -11,6 +12,23 @@ pub struct TabStateOptions {
 }
 
 impl TabStateOptions {
+    pub fn new(word_wrap: u8, right_to_left: u8, show_unicode_control: u8, unk: u8) -> Self {
+        Self {
+            word_wrap,
+            right_to_left,
+            show_unicode_control,
+            unk,
+        }
+    }
+    /// Returns the raw option bytes, in the order they are stored in the file.
+    pub fn as_bytes(&self) -> [u8; OPTIONS_SIZE] {
+        [
+            self.word_wrap,
+            self.right_to_left,
+            self.show_unicode_control,
+            self.unk,
+        ]
+    }
     pub fn word_wrap(&self) -> bool {
         self.word_wrap != 0
     }
@@ -26,4 +44,4 @@ impl TabStateOptions {
 }
 
 pub const OPTIONS_SIZE: usize = 0x4;
-const _: () = assert!(std::mem::size_of::<TabStateOptions>() == OPTIONS_SIZE);
+const _: () = assert!(core::mem::size_of::<TabStateOptions>() == OPTIONS_SIZE);
@@ -0,0 +1,73 @@
+#![doc = "A reader over a byte slice that hands out references into the slice. This is the part of `buffer_reader::BufferReader` this crate used, but it returns our own error type, so it works without std."]
+
+use crate::error::{Error, ErrorKind, Result};
+use alloc::format;
+use bytemuck::AnyBitPattern;
+
+pub struct BufferReader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BufferReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            position: 0,
+        }
+    }
+    /// The amount of bytes left to read.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// The offset of the next byte to be read.
+    #[inline(always)]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+    /// Returns the byte `offset` bytes ahead of the current position, without advancing.
+    pub fn peek_byte(&self, offset: usize) -> Result<u8> {
+        self.buffer
+            .get(self.position + offset)
+            .copied()
+            .ok_or_else(|| eof(offset + 1, self.len()))
+    }
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.len() {
+            return Err(eof(len, self.len()));
+        }
+
+        let bytes = &self.buffer[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+    /// Reads a `T` from the buffer. Every type we read is made of bytes, so there is no alignment to
+    /// worry about.
+    pub fn read_t<T: AnyBitPattern>(&mut self) -> Result<&'a T> {
+        let bytes = self.read_bytes(core::mem::size_of::<T>())?;
+        bytemuck::try_from_bytes(bytes).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Could not read type: {e:?}"),
+            )
+        })
+    }
+}
+
+/// The offset of `slice` in `buffer`. `slice` has to be a reference into `buffer`.
+#[inline(always)]
+pub(crate) fn offset_of(buffer: &[u8], slice: &[u8]) -> usize {
+    slice.as_ptr() as usize - buffer.as_ptr() as usize
+}
+
+fn eof(wanted: usize, remaining: usize) -> Error {
+    Error::new(
+        ErrorKind::UnexpectedEof,
+        format!("Tried to read {wanted} bytes, but only {remaining} bytes are left in the buffer"),
+    )
+}
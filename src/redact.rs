@@ -0,0 +1,280 @@
+#![doc = "Redacts TabState files so they can be shared. Text and paths are overwritten in place with placeholders of the same length, so every varint, offset and option stays the same, and the CRCs are recalculated. Anything else that could give the text away, like the content hash and half written chunks, is replaced too."]
+
+use crate::consts::CRC_START;
+use crate::crc::crc32;
+use crate::reader::offset_of;
+use crate::refs::tabstate::buffer::utf16le_chars;
+use crate::refs::tabstate::TabStateRefs;
+use crate::spans::{skim_field_spans, SpanKind};
+use crate::text::CodePage;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// The char every redacted char is replaced with.
+pub const PLACEHOLDER: u16 = b'x' as u16;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RedactOptions {
+    /// Also redact the text added by each edit chunk.
+    pub chunks: bool,
+}
+
+/// Returns a copy of the provided TabState buffer with the text, the file path and, if
+/// `options.chunks` is set, the chunk text replaced by placeholders. Line breaks and tabs in the text,
+/// and separators in the path, are kept, so the redacted file has the same shape as the original.
+///
+/// The content hash is the SHA-256 of the saved file, so anyone could check a guess of the text against
+/// it. It's replaced with the hash of the redacted text, as if that had been saved. Trailing bytes are
+/// usually a chunk Notepad was still writing, so they are zeroed, but keep their length. Every CRC is
+/// recalculated.
+pub fn redact(buffer: &[u8], options: RedactOptions) -> crate::error::Result<Vec<u8>> {
+    let refs = TabStateRefs::from_buffer(buffer)?;
+    let mut redacted = buffer.to_vec();
+
+    if let Some(metadata) = refs.get_metadata() {
+        let path = metadata.get_raw_path();
+        redact_wide(
+            &mut redacted,
+            offset_of(buffer, path),
+            path.len(),
+            is_path_separator,
+        );
+    }
+
+    let text = refs.get_raw_text_buffer();
+    let text_offset = offset_of(buffer, text);
+    redact_wide(&mut redacted, text_offset, text.len(), is_line_break);
+
+    if let Some(metadata) = refs.get_metadata() {
+        // The redacted text is all ASCII, so the code page doesn't matter.
+        let text = String::from_utf16_lossy(&utf16le_chars(
+            &redacted[text_offset..text_offset + text.len()],
+        ));
+        let hash = metadata
+            .to_metadata()
+            .calculate_content_hash(&text, CodePage::default());
+        let offset = offset_of(buffer, &metadata.get_content_hash()[..]);
+        redacted[offset..offset + hash.len()].copy_from_slice(&hash);
+    }
+
+    let footer_crc = offset_of(buffer, &refs.get_footer().crc[..]);
+    let crc = crc32(&redacted[CRC_START..footer_crc]);
+    redacted[footer_crc..footer_crc + 4].copy_from_slice(&crc.to_be_bytes());
+
+    for chunk in refs.get_chunks() {
+        if options.chunks {
+            let text = chunk.get_raw_added_text();
+            redact_wide(
+                &mut redacted,
+                offset_of(buffer, text),
+                text.len(),
+                is_line_break,
+            );
+        }
+
+        let start = offset_of(buffer, chunk.get_position().get_buffer());
+        let chunk_crc = offset_of(buffer, &chunk.get_crc_bytes()[..]);
+        let crc = crc32(&redacted[start..chunk_crc]);
+        redacted[chunk_crc..chunk_crc + 4].copy_from_slice(&crc.to_be_bytes());
+    }
+
+    let trailing = refs.get_trailing_bytes();
+    let offset = offset_of(buffer, trailing);
+    redacted[offset..offset + trailing.len()].fill(0);
+
+    Ok(redacted)
+}
+
+/// Redacts a file that `redact` can't parse, like one with an unknown version, state or unk2. Only
+/// the fields `skim_field_spans` finds are kept: the header, the metadata and the lengths. The path
+/// and the text are replaced the same way `redact` does it, and the content hash is zeroed. Every
+/// other byte is zeroed too, since we can't tell what it is, and the CRCs are left zeroed, since we
+/// don't know where they are.
+pub fn redact_unparsed(buffer: &[u8]) -> Vec<u8> {
+    let mut redacted = vec![0; buffer.len()];
+    for span in skim_field_spans(buffer) {
+        let range = span.offset..span.end();
+        match (span.field.as_str(), span.kind) {
+            (_, SpanKind::Uncovered) | ("metadata.content_hash", _) => {}
+            ("metadata.file_path", _) => {
+                redacted[range.clone()].copy_from_slice(&buffer[range]);
+                redact_wide(&mut redacted, span.offset, span.len, is_path_separator);
+            }
+            ("text", _) => {
+                redacted[range.clone()].copy_from_slice(&buffer[range]);
+                redact_wide(&mut redacted, span.offset, span.len, is_line_break);
+            }
+            _ => redacted[range.clone()].copy_from_slice(&buffer[range]),
+        }
+    }
+
+    redacted
+}
+
+fn is_path_separator(c: u8) -> bool {
+    matches!(c, b'\\' | b'/' | b':' | b'.')
+}
+
+fn is_line_break(c: u8) -> bool {
+    matches!(c, b'\r' | b'\n' | b'\t')
+}
+
+/// Replaces each UTF-16LE char in `buffer[offset..offset + len]` with `PLACEHOLDER`, unless it is an
+/// ASCII char that `keep` returns true for.
+fn redact_wide(buffer: &mut [u8], offset: usize, len: usize, keep: impl Fn(u8) -> bool) {
+    for c in buffer[offset..offset + len].chunks_exact_mut(2) {
+        let value = u16::from_le_bytes([c[0], c[1]]);
+        if value < 0x80 && keep(value as u8) {
+            continue;
+        }
+        c.copy_from_slice(&PLACEHOLDER.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enums::Encoding;
+    use crate::options::TabStateOptions;
+    use crate::redact::{redact, redact_unparsed, RedactOptions};
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::spans::{get_field_spans, skim_field_spans};
+    use crate::tabstate::chunk::EditChunk;
+    use crate::tabstate::TabState;
+    use crate::test_util::saved_tab;
+    use crate::text::CodePage;
+
+    fn tab() -> TabState {
+        let mut tab = TabState {
+            options: TabStateOptions::new(1, 0, 1, 1),
+            // Long enough that the length takes two varint bytes, with a surrogate pair.
+            text: "password: hunter2 😀\r".repeat(10),
+            chunks: vec![EditChunk {
+                position: 4,
+                deletion_count: 1,
+                addition_count: None,
+                added_text: "new secret".to_string(),
+                crc: None,
+            }],
+            ..saved_tab()
+        };
+        tab.metadata.as_mut().unwrap().encoding = Encoding::UTF16LE;
+        tab
+    }
+
+    /// The redacted file should have every field at the same offset, with the same size, and only
+    /// the redacted values should change.
+    #[test]
+    fn keeps_structure() {
+        let bytes = tab().to_bytes();
+        let redacted = redact(&bytes, RedactOptions { chunks: true }).unwrap();
+        assert_eq!(redacted.len(), bytes.len());
+
+        let spans = get_field_spans(&bytes).unwrap();
+        let redacted_spans = get_field_spans(&redacted).unwrap();
+        assert_eq!(spans.len(), redacted_spans.len());
+        for (a, b) in spans.iter().zip(&redacted_spans) {
+            assert_eq!((a.offset, a.len, &a.field), (b.offset, b.len, &b.field));
+        }
+
+        let refs = TabStateRefs::from_buffer(&redacted).unwrap();
+        assert!(refs.is_crc_valid());
+
+        let original = TabState::from_buffer(&bytes).unwrap();
+        let parsed = refs.to_tab_state();
+        let metadata = parsed.metadata.as_ref().unwrap();
+        assert_eq!(metadata.path, r"x:\xxxxx\xxxx\xxxxxxx\xxxx.xxx");
+        assert_eq!(metadata.encoding, Encoding::UTF16LE);
+        assert_eq!(
+            metadata.content_hash,
+            metadata.calculate_content_hash(&parsed.text, CodePage::default())
+        );
+        assert_eq!(parsed.text, "xxxxxxxxxxxxxxxxxxxx\r".repeat(10));
+        assert_eq!(parsed.options, original.options);
+        assert_eq!(parsed.cursor_end, original.cursor_end);
+        assert_eq!(parsed.chunks[0].added_text, "xxxxxxxxxx");
+        assert_eq!(parsed.chunks[0].position, 4);
+    }
+
+    #[test]
+    fn chunks_are_optional() {
+        let bytes = tab().to_bytes();
+        let redacted = redact(&bytes, RedactOptions::default()).unwrap();
+
+        let refs = TabStateRefs::from_buffer(&redacted).unwrap();
+        assert!(refs.is_crc_valid());
+        assert_eq!(refs.to_tab_state().chunks[0].added_text, "new secret");
+    }
+
+    /// Nothing that was typed should make it into the redacted file, not even from a chunk that was
+    /// only half written.
+    #[test]
+    fn leaves_no_text_behind() {
+        let marker = "MARKER";
+        let mut tab = tab();
+        tab.chunks[0].added_text = marker.to_string();
+        let mut bytes = tab.to_bytes();
+        // Half a chunk: position, deletion count, addition count, then the text without a CRC.
+        bytes.extend_from_slice(&[0x01, 0x00, 0x06]);
+        bytes.extend(marker.encode_utf16().flat_map(u16::to_le_bytes));
+        let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+        assert_eq!(refs.get_trailing_bytes().len(), 3 + marker.len() * 2);
+
+        let redacted = redact(&bytes, RedactOptions { chunks: true }).unwrap();
+        assert_eq!(redacted.len(), bytes.len());
+        let wide = marker
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        let contains = |needle: &[u8]| redacted.windows(needle.len()).any(|w| w == needle);
+        assert!(!contains(&wide));
+        assert!(!contains(marker.as_bytes()));
+
+        let refs = TabStateRefs::from_buffer(&redacted).unwrap();
+        assert!(refs.get_trailing_bytes().iter().all(|b| *b == 0));
+        assert_ne!(
+            refs.get_metadata().unwrap().get_content_hash(),
+            &tab.metadata.as_ref().unwrap().content_hash
+        );
+    }
+
+    /// A file with an unknown unk2 can't be parsed, but the header and metadata can still be kept.
+    #[test]
+    fn redacts_unparsed_files() {
+        let mut tab = tab();
+        tab.metadata.as_mut().unwrap().unk2 = 7;
+        tab.chunks[0].added_text = "MARKER".to_string();
+        let bytes = tab.to_bytes();
+        assert!(TabStateRefs::from_buffer(&bytes).is_err());
+
+        let redacted = redact_unparsed(&bytes);
+        assert_eq!(redacted.len(), bytes.len());
+        let wide = |s: &str| {
+            s.encode_utf16()
+                .flat_map(u16::to_le_bytes)
+                .collect::<Vec<_>>()
+        };
+        for secret in ["MARKER", "hunter2", "Nord"] {
+            let secret = wide(secret);
+            assert!(!redacted.windows(secret.len()).any(|w| w == secret));
+        }
+
+        // The fields the skim finds are kept, except the path, the text and the hash.
+        let spans = skim_field_spans(&bytes);
+        let redacted_spans = skim_field_spans(&redacted);
+        assert_eq!(spans.len(), redacted_spans.len());
+        for (a, b) in spans.iter().zip(&redacted_spans) {
+            assert_eq!((a.offset, a.len, &a.field), (b.offset, b.len, &b.field));
+            match a.field.as_str() {
+                "metadata.file_path" => {
+                    assert_eq!(b.value, r#""x:\\xxxxx\\xxxx\\xxxxxxx\\xxxx.xxx""#)
+                }
+                "metadata.content_hash" => assert_eq!(b.value, "00".repeat(0x20)),
+                "text" | "uncovered" => {}
+                _ => assert_eq!(a.value, b.value, "{}", a.field),
+            }
+        }
+        let text = redacted_spans.iter().find(|s| s.field == "text").unwrap();
+        assert!(redacted[text.end()..].iter().all(|b| *b == 0));
+    }
+}
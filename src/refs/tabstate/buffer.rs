@@ -1,23 +1,35 @@
+use crate::error::{Error, ErrorKind};
+use crate::reader::BufferReader;
 use crate::refs::varint::VarIntRef;
-use buffer_reader::BufferReader;
-use widestring::WideStr;
+use alloc::borrow::Cow;
+use alloc::format;
+use widestring::U16String;
+
+/// A varint length in chars, followed by that many UTF-16LE chars.
 #[derive(Copy, Clone)]
 pub struct TabStateBufferRef<'a> {
     buffer_len: VarIntRef<'a>,
-    buffer: &'a WideStr,
+    /// The raw UTF-16LE bytes. The text can start at any offset in the file, so we keep the bytes
+    /// instead of a `&[u16]`, which would have to be aligned.
+    buffer: &'a [u8],
 }
 
 impl<'a> TabStateBufferRef<'a> {
-    pub fn new(buffer_len: VarIntRef<'a>, buffer: &'a WideStr) -> Self {
+    pub fn new(buffer_len: VarIntRef<'a>, buffer: &'a [u8]) -> Self {
         TabStateBufferRef { buffer_len, buffer }
     }
-    pub fn from_reader(br: &mut BufferReader<'a>) -> std::io::Result<Self> {
+    pub fn from_reader(br: &mut BufferReader<'a>) -> crate::error::Result<Self> {
         // Length comes first
         let buffer_len = VarIntRef::from_reader(br)?;
         let decoded_size = buffer_len.decode();
-        // Then we read the bytes and convert it to a `WideStr`
-        let str_bytes = br.read_slice_t(decoded_size)?;
-        let buffer = WideStr::from_slice(str_bytes);
+        // Then we read the bytes. Each char is two bytes.
+        let byte_len = decoded_size.checked_mul(2).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Text buffer length is too large: {decoded_size}"),
+            )
+        })?;
+        let buffer = br.read_bytes(byte_len)?;
         Ok(Self::new(buffer_len, buffer))
     }
     pub fn decode_buffer_len(&self) -> usize {
@@ -26,7 +38,98 @@ impl<'a> TabStateBufferRef<'a> {
     pub fn get_buffer_len(&self) -> VarIntRef<'a> {
         self.buffer_len
     }
-    pub fn get_buffer(&self) -> &'a WideStr {
+    /// The UTF-16 chars of the text. Borrows from the file when it can, see `utf16le_chars`.
+    pub fn get_chars(&self) -> Cow<'a, [u16]> {
+        utf16le_chars(self.buffer)
+    }
+    /// Decodes the text into a `U16String`.
+    #[deprecated(note = "always copies the text, use `get_chars`, which borrows it when it can")]
+    pub fn get_buffer(&self) -> U16String {
+        decode_utf16le(self.buffer)
+    }
+    /// Get a reference to the raw UTF-16LE bytes of the text.
+    pub fn get_raw_buffer(&self) -> &'a [u8] {
         self.buffer
     }
 }
+
+/// Gets the chars out of UTF-16LE bytes. If the bytes happen to be aligned for `u16` and we're on a
+/// little endian host, the bytes already are the chars, so they're borrowed. Otherwise, they're copied
+/// out one char at a time. A trailing odd byte is ignored.
+pub fn utf16le_chars(bytes: &[u8]) -> Cow<'_, [u16]> {
+    let bytes = &bytes[..bytes.len() & !1];
+
+    #[cfg(target_endian = "little")]
+    {
+        if let Ok(chars) = bytemuck::try_cast_slice(bytes) {
+            return Cow::Borrowed(chars);
+        }
+    }
+
+    Cow::Owned(
+        bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect(),
+    )
+}
+
+/// Decodes UTF-16LE bytes into a `U16String`. A trailing odd byte is ignored.
+pub fn decode_utf16le(bytes: &[u8]) -> U16String {
+    utf16le_chars(bytes).into_owned().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::refs::tabstate::buffer::{decode_utf16le, utf16le_chars};
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::tabstate::TabState;
+    use crate::test_util::unsaved_tab;
+    use alloc::borrow::Cow;
+
+    /// "A😀" followed by an odd byte, which should be ignored.
+    const BYTES: [u8; 7] = [0x41, 0x00, 0x3D, 0xD8, 0x00, 0xDE, 0xFF];
+    const CHARS: [u16; 3] = [0x0041, 0xD83D, 0xDE00];
+
+    #[test]
+    fn decodes_at_any_alignment() {
+        // Backed by u16s, so offset 0 is aligned and offset 1 isn't.
+        let mut storage = [0u16; 8];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut storage);
+        for offset in [0, 1] {
+            bytes[offset..offset + BYTES.len()].copy_from_slice(&BYTES);
+            let chars = utf16le_chars(&bytes[offset..offset + BYTES.len()]);
+            assert_eq!(&chars[..], &CHARS[..]);
+            assert_eq!(
+                decode_utf16le(&bytes[offset..offset + BYTES.len()]).as_slice(),
+                &CHARS[..]
+            );
+
+            // Only aligned bytes on a little endian host can be borrowed.
+            let borrowed = matches!(chars, Cow::Borrowed(_));
+            assert_eq!(borrowed, offset == 0 && cfg!(target_endian = "little"));
+        }
+    }
+
+    #[test]
+    fn parses_text_at_any_alignment() {
+        let tab = TabState {
+            text: "Hello 😀".to_string(),
+            chunks: vec![],
+            ..unsaved_tab()
+        };
+        let bytes = tab.to_bytes();
+
+        let mut storage = vec![0u16; bytes.len() / 2 + 1];
+        let buffer: &mut [u8] = bytemuck::cast_slice_mut(&mut storage);
+        for offset in [0, 1] {
+            buffer[offset..offset + bytes.len()].copy_from_slice(&bytes);
+            let refs = TabStateRefs::from_buffer(&buffer[offset..offset + bytes.len()]).unwrap();
+            assert_eq!(
+                refs.get_buffer_chars().as_ref(),
+                tab.text.encode_utf16().collect::<Vec<_>>()
+            );
+            assert_eq!(refs.to_tab_state().text, tab.text);
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::crc::crc32;
+use crate::reader::BufferReader;
+use crate::refs::tabstate::buffer::{decode_utf16le, utf16le_chars};
+use crate::refs::varint::VarIntRef;
+use crate::tabstate::chunk::EditChunk;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec;
+use widestring::U16String;
+
+/// An edit that Notepad appends to the end of the file while the tab is still open. Each chunk says
+/// where in the text the edit happened, how many chars were deleted there, and the chars that were
+/// added, followed by a CRC32 of the chunk.
+#[derive(Copy, Clone)]
+pub struct EditChunkRef<'a> {
+    position: VarIntRef<'a>,
+    deletion_count: VarIntRef<'a>,
+    addition_count: VarIntRef<'a>,
+    /// Raw UTF-16LE bytes of the added text.
+    added_text: &'a [u8],
+    crc: &'a [u8; 4],
+}
+
+impl<'a> EditChunkRef<'a> {
+    pub fn new(
+        position: VarIntRef<'a>,
+        deletion_count: VarIntRef<'a>,
+        addition_count: VarIntRef<'a>,
+        added_text: &'a [u8],
+        crc: &'a [u8; 4],
+    ) -> Self {
+        Self {
+            position,
+            deletion_count,
+            addition_count,
+            added_text,
+            crc,
+        }
+    }
+    pub fn from_reader(br: &mut BufferReader<'a>) -> crate::error::Result<Self> {
+        let position = VarIntRef::from_reader(br)?;
+        let deletion_count = VarIntRef::from_reader(br)?;
+        let addition_count = VarIntRef::from_reader(br)?;
+        let added_text = br.read_bytes(addition_count.decode().saturating_mul(2))?;
+        let crc = br.read_t()?;
+
+        Ok(Self::new(
+            position,
+            deletion_count,
+            addition_count,
+            added_text,
+            crc,
+        ))
+    }
+    pub fn get_position(&self) -> VarIntRef<'a> {
+        self.position
+    }
+    /// The position in chars in the text buffer where this edit happened.
+    pub fn decode_position(&self) -> usize {
+        self.position.decode()
+    }
+    pub fn get_deletion_count(&self) -> VarIntRef<'a> {
+        self.deletion_count
+    }
+    /// The amount of chars deleted at the position.
+    pub fn decode_deletion_count(&self) -> usize {
+        self.deletion_count.decode()
+    }
+    pub fn get_addition_count(&self) -> VarIntRef<'a> {
+        self.addition_count
+    }
+    /// The amount of chars added at the position.
+    pub fn decode_addition_count(&self) -> usize {
+        self.addition_count.decode()
+    }
+    /// Decodes the added text into a `U16String`.
+    #[deprecated(
+        note = "always copies the text, use `get_added_chars`, which borrows it when it can"
+    )]
+    pub fn get_added_text(&self) -> U16String {
+        decode_utf16le(self.added_text)
+    }
+    /// The UTF-16 chars of the added text, borrowed from the file if they are aligned.
+    pub fn get_added_chars(&self) -> Cow<'a, [u16]> {
+        utf16le_chars(self.added_text)
+    }
+    /// Get a reference to the raw UTF-16LE bytes of the added text.
+    pub fn get_raw_added_text(&self) -> &'a [u8] {
+        self.added_text
+    }
+    /// The CRC is stored big endian, same as the footer.
+    pub fn get_crc(&self) -> u32 {
+        u32::from_be_bytes(*self.crc)
+    }
+    pub fn get_crc_bytes(&self) -> &'a [u8; 4] {
+        self.crc
+    }
+    /// Calculates the CRC32 of the chunk, which covers everything in the chunk except the CRC.
+    pub fn calculate_crc(&self) -> u32 {
+        let mut buffer = vec![];
+        buffer.extend_from_slice(self.position.get_buffer());
+        buffer.extend_from_slice(self.deletion_count.get_buffer());
+        buffer.extend_from_slice(self.addition_count.get_buffer());
+        buffer.extend_from_slice(self.added_text);
+        crc32(&buffer[..])
+    }
+    /// Copies the chunk into an owned `EditChunk`. The addition count and CRC are kept as they were in
+    /// the file. Unpaired surrogates in the added text are replaced with U+FFFD, same as
+    /// `TabStateRefs::to_tab_state`.
+    pub fn to_edit_chunk(&self) -> EditChunk {
+        EditChunk {
+            position: self.decode_position(),
+            deletion_count: self.decode_deletion_count(),
+            addition_count: Some(self.decode_addition_count()),
+            added_text: String::from_utf16_lossy(&self.get_added_chars()),
+            crc: Some(self.get_crc()),
+        }
+    }
+}
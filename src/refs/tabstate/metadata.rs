@@ -1,10 +1,14 @@
-use crate::consts::{CARRIAGE_TYPES, ENCODINGS};
+use crate::consts::{CARRIAGE_TYPES, ENCODINGS, MAX_VARINT_LEN};
 use crate::enums::{CarriageType, Encoding};
-use crate::refs::tabstate::buffer::TabStateBufferRef;
+use crate::error::{Error, ErrorKind};
+use crate::reader::BufferReader;
+use crate::refs::tabstate::buffer::{decode_utf16le, TabStateBufferRef};
 use crate::refs::varint::VarIntRef;
-use buffer_reader::BufferReader;
-use std::io::{Error, ErrorKind};
-use widestring::WideStr;
+use crate::tabstate::metadata::Metadata;
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use widestring::U16String;
 
 #[derive(Copy, Clone)]
 pub struct TabStateMetadata<'a> {
@@ -22,6 +26,7 @@ pub struct TabStateMetadata<'a> {
 }
 
 impl<'a> TabStateMetadata<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         file_path: TabStateBufferRef<'a>,
         full_buffer_size: VarIntRef<'a>,
@@ -43,7 +48,7 @@ impl<'a> TabStateMetadata<'a> {
             unk2,
         }
     }
-    pub fn from_reader(br: &mut BufferReader<'a>) -> std::io::Result<Self> {
+    pub fn from_reader(br: &mut BufferReader<'a>) -> crate::error::Result<Self> {
         // Get the file path.
         let file_path = TabStateBufferRef::from_reader(br)?;
 
@@ -52,28 +57,13 @@ impl<'a> TabStateMetadata<'a> {
 
         // The metadata structure starts with the encoding
         let encoding = br.read_t::<Encoding>()?;
-        if !ENCODINGS.contains(&encoding.as_value()) {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "Unknown encoding Expected one of: {ENCODINGS:?}. Got: {:X}",
-                    encoding
-                ),
-            ));
-        }
+        check_encoding(encoding.as_value())?;
         // Then the return carriage type
         let return_carriage = br.read_t::<CarriageType>()?;
-        if !CARRIAGE_TYPES.contains(&return_carriage.as_value()) {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "Unknown file variant. Expected one of: {CARRIAGE_TYPES:?}. Got: {:X}",
-                    return_carriage
-                ),
-            ));
-        }
+        check_carriage_type(return_carriage.as_value())?;
 
-        let filetime = VarIntRef::from_reader(br)?;
+        // A FILETIME is a u64, which might not fit in a usize.
+        let filetime = VarIntRef::from_reader_bounded(br, MAX_VARINT_LEN, u64::BITS)?;
         let content_hash = br.read_t()?;
         let unk = br.read_t()?;
         let unk2 = br.read_t()?;
@@ -96,8 +86,19 @@ impl<'a> TabStateMetadata<'a> {
     }
     /// Get a reference to the path of the file this TabState represents. Unsaved files do not have
     /// a path.
-    pub fn get_path(&self) -> &'a WideStr {
-        self.file_path.get_buffer()
+    #[deprecated(
+        note = "always copies the path, use `get_path_chars`, which borrows it when it can"
+    )]
+    pub fn get_path(&self) -> U16String {
+        decode_utf16le(self.file_path.get_raw_buffer())
+    }
+    /// The UTF-16 chars of the path, borrowed from the file if they are aligned.
+    pub fn get_path_chars(&self) -> Cow<'a, [u16]> {
+        self.file_path.get_chars()
+    }
+    /// Get a reference to the raw UTF-16LE bytes of the file path.
+    pub fn get_raw_path(&self) -> &'a [u8] {
+        self.file_path.get_raw_buffer()
     }
     /// Get a reference to the full buffer size VarInt that represents the size in charsof the text
     /// file on disk, if available.
@@ -122,4 +123,45 @@ impl<'a> TabStateMetadata<'a> {
     pub fn get_unk2(&'a self) -> &'a u8 {
         self.unk2
     }
+    /// Copies the metadata into an owned `Metadata`. Lengths are kept as they were in the file. Unpaired
+    /// surrogates in the path are replaced with U+FFFD, same as `TabStateRefs::to_tab_state`.
+    pub fn to_metadata(&self) -> Metadata {
+        Metadata {
+            path: String::from_utf16_lossy(&self.get_path_chars()),
+            path_len: Some(self.file_path.decode_buffer_len()),
+            full_buffer_size: Some(self.full_buffer_size.decode()),
+            encoding: *self.encoding,
+            carriage_type: *self.carriage_type,
+            filetime: self.filetime.decode_lossless() as u64,
+            content_hash: *self.content_hash,
+            unk: *self.unk,
+            unk2: *self.unk2,
+        }
+    }
+}
+
+/// Returns an error if the encoding byte isn't one we know about.
+pub(crate) fn check_encoding(encoding: u8) -> crate::error::Result<()> {
+    if !ENCODINGS.contains(&encoding) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unknown encoding Expected one of: {ENCODINGS:?}. Got: {encoding:X}"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns an error if the carriage type byte isn't one we know about.
+pub(crate) fn check_carriage_type(carriage_type: u8) -> crate::error::Result<()> {
+    if !CARRIAGE_TYPES.contains(&carriage_type) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Unknown file variant. Expected one of: {CARRIAGE_TYPES:?}. Got: {carriage_type:X}"
+            ),
+        ));
+    }
+
+    Ok(())
 }
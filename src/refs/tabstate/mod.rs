@@ -1,18 +1,28 @@
 #![doc = "TabState references to each part of a TabState file. This covers all variants of the tabstate file, so some parts are optional"]
 
-use crate::consts::{FILE_STATE_SAVED, FILE_STATE_UNSAVED, METADATA_UNK2};
+use crate::anomaly::{find_anomalies, Anomaly};
+use crate::consts::{CRC_START, FILE_STATE_SAVED, FILE_STATE_UNSAVED, METADATA_UNK2};
+use crate::crc::crc32;
+use crate::error::{Error, ErrorKind};
 use crate::footer::TabStateFooter;
 use crate::header::Header;
 use crate::options::TabStateOptions;
-use crate::refs::tabstate::buffer::TabStateBufferRef;
+use crate::reader::{offset_of, BufferReader};
+use crate::refs::tabstate::buffer::{decode_utf16le, TabStateBufferRef};
+use crate::refs::tabstate::chunk::EditChunkRef;
 use crate::refs::tabstate::cursor::TabStateCursor;
 use crate::refs::tabstate::metadata::TabStateMetadata;
 use crate::refs::varint::VarIntRef;
-use buffer_reader::BufferReader;
-use std::io::{Error, ErrorKind};
-use widestring::WideStr;
+use crate::tabstate::TabState;
+use crate::version::{detect_version, FormatVersion};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use widestring::U16String;
 
 pub mod buffer;
+pub mod chunk;
 pub mod cursor;
 pub mod metadata;
 
@@ -25,17 +35,28 @@ pub struct TabStateRefs<'a> {
     options: &'a TabStateOptions,
     text_buffer: TabStateBufferRef<'a>,
     footer: &'a TabStateFooter,
+    chunks: Vec<EditChunkRef<'a>>,
+    trailing: &'a [u8],
+    buffer: &'a [u8],
+    version: FormatVersion,
+    anomalies: Vec<Anomaly>,
 }
 
 impl<'a> TabStateRefs<'a> {
-    /// Returns a new `TabStateRefs` object containing the provided refs.
-    pub fn new(
+    /// Returns a new `TabStateRefs` object containing the provided refs. Every ref has to point into
+    /// `buffer`, since offsets and CRCs are worked out from where they are in it, so only the parser
+    /// gets to build these.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
         header: &'a Header,
         metadata: Option<TabStateMetadata<'a>>,
         cursor: TabStateCursor<'a>,
         options: &'a TabStateOptions,
         text_buffer: TabStateBufferRef<'a>,
         footer: &'a TabStateFooter,
+        chunks: Vec<EditChunkRef<'a>>,
+        trailing: &'a [u8],
+        buffer: &'a [u8],
     ) -> TabStateRefs<'a> {
         Self {
             header,
@@ -44,10 +65,23 @@ impl<'a> TabStateRefs<'a> {
             options,
             text_buffer,
             footer,
+            chunks,
+            trailing,
+            buffer,
+            version: FormatVersion::LATEST,
+            anomalies: vec![],
         }
     }
+    /// The format version the file was parsed as.
+    pub fn get_version(&self) -> FormatVersion {
+        self.version
+    }
+    /// Get a reference to the header for the file.
+    pub fn get_header(&self) -> &'a Header {
+        self.header
+    }
     // Returns the `SavedStateRefs` for this object, if the buffer is in a saved state.
-    pub fn get_metadata(&self) -> Option<TabStateMetadata> {
+    pub fn get_metadata(&self) -> Option<TabStateMetadata<'_>> {
         self.metadata
     }
     /// Get a reference to the cursor start VarInt.
@@ -58,69 +92,115 @@ impl<'a> TabStateRefs<'a> {
     pub fn get_cursor_end(&'a self) -> VarIntRef<'a> {
         self.cursor.get_cursor_end()
     }
+    /// Get a reference to the editor options for the tab.
+    pub fn get_options(&self) -> &'a TabStateOptions {
+        self.options
+    }
     /// Get a reference to the main text buffer size for the TabState.
     pub fn get_buffer_len(&'a self) -> VarIntRef<'a> {
         self.text_buffer.get_buffer_len()
     }
-    /// Get a reference to the main text buffer for the TabState.
-    pub fn get_buffer(&self) -> &'a WideStr {
-        self.text_buffer.get_buffer()
+    /// Decodes the main text buffer for the TabState.
+    #[deprecated(
+        note = "always copies the text, use `get_buffer_chars`, which borrows it when it can"
+    )]
+    pub fn get_buffer(&self) -> U16String {
+        decode_utf16le(self.text_buffer.get_raw_buffer())
+    }
+    /// The UTF-16 chars of the main text buffer, borrowed from the file if they are aligned.
+    pub fn get_buffer_chars(&self) -> Cow<'a, [u16]> {
+        self.text_buffer.get_chars()
+    }
+    /// Get a reference to the raw UTF-16LE bytes of the main text buffer.
+    pub fn get_raw_text_buffer(&self) -> &'a [u8] {
+        self.text_buffer.get_raw_buffer()
     }
     /// Get a reference to the footer for the file.
     pub fn get_footer(&self) -> &'a TabStateFooter {
         self.footer
     }
-    /// Parse the TabState file from a given buffer.
-    pub fn from_buffer(buffer: &'a [u8]) -> std::io::Result<Self> {
+    /// Get the edit chunks that come after the footer. Only tabs that are still open in Notepad have
+    /// these.
+    pub fn get_chunks(&self) -> &[EditChunkRef<'a>] {
+        &self.chunks[..]
+    }
+    /// Get the bytes after the last edit chunk that could not be read as a chunk. This is usually a
+    /// chunk that Notepad was still writing.
+    pub fn get_trailing_bytes(&self) -> &'a [u8] {
+        self.trailing
+    }
+    /// Get everything unexpected that was found while parsing, like trailing bytes, unknown values
+    /// and CRC mismatches. Only `from_buffer` fills these in.
+    pub fn get_anomalies(&self) -> &[Anomaly] {
+        &self.anomalies[..]
+    }
+    /// Get the whole buffer the refs were parsed from.
+    pub fn get_raw_buffer(&self) -> &'a [u8] {
+        self.buffer
+    }
+    /// Calculates the CRC32 the footer should have.
+    pub fn calculate_crc(&self) -> u32 {
+        let end = offset_of(self.buffer, &self.footer.crc);
+        crc32(&self.buffer[CRC_START..end])
+    }
+    /// Returns true if the footer CRC and the CRC of every edit chunk match, and there are no trailing
+    /// bytes. A file that Notepad is in the middle of writing will usually fail this.
+    pub fn is_crc_valid(&self) -> bool {
+        self.footer.get_crc() == self.calculate_crc()
+            && self.chunks.iter().all(|c| c.get_crc() == c.calculate_crc())
+            && self.trailing.is_empty()
+    }
+    /// Copies the referenced data into an owned `TabState`. Lengths and the CRC are kept as they were
+    /// in the file. The text is UTF-16 that doesn't have to be valid, but `String` has to be, so
+    /// unpaired surrogates are replaced with U+FFFD. Such a file won't write back out the same, so use
+    /// `get_buffer_chars` if you need the exact text.
+    pub fn to_tab_state(&self) -> TabState {
+        TabState {
+            header_unk: self.header.magic[2],
+            metadata: self.metadata.map(|m| m.to_metadata()),
+            cursor_start: self.cursor.decode_cursor_start(),
+            cursor_end: self.cursor.decode_cursor_end(),
+            options: *self.options,
+            text: String::from_utf16_lossy(&self.get_buffer_chars()),
+            text_len: Some(self.text_buffer.decode_buffer_len()),
+            the_number_zero: self.footer.the_number_zero,
+            crc: Some(self.footer.get_crc()),
+            chunks: self.chunks.iter().map(|c| c.to_edit_chunk()).collect(),
+        }
+    }
+    /// Parse the TabState file from a given buffer. The format version is detected first, and files
+    /// with a version we don't know are rejected with `ErrorKind::Unsupported`.
+    pub fn from_buffer(buffer: &'a [u8]) -> crate::error::Result<Self> {
+        let version = detect_version(buffer)?;
+        let mut refs = match version {
+            FormatVersion::V1 => Self::from_buffer_v1(buffer)?,
+        };
+        refs.version = version;
+        // Trailing bytes, odd values and bad CRCs don't stop us from parsing, but we keep track of
+        // them so the caller can report them.
+        refs.anomalies = find_anomalies(&refs);
+
+        Ok(refs)
+    }
+    fn from_buffer_v1(buffer: &'a [u8]) -> crate::error::Result<Self> {
         let mut br = BufferReader::new(buffer);
 
         let header = br.read_t::<Header>()?;
 
-        // I know that the magic is technically just NP, and that the third byte can change, but if
-        // it isn't I am going to return an error, anyway, so let's just check it here.
-        if &header.magic != b"NP\0" {
-            return Err(Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "Magic bytes invalid. Should be \"NP\" and a null byte. Read: \"{}\" raw: {:?}",
-                    unsafe { std::str::from_utf8_unchecked(&header.magic) },
-                    header.magic
-                ),
-            ));
-        }
-
         // We have to match as u8s, otherwise the compiler thinks the final case is unreachable, which
         // is not true in this case, and the code will be optimized out.
         let metadata = match header.state as u8 {
             FILE_STATE_SAVED => Some(TabStateMetadata::from_reader(&mut br)?),
             FILE_STATE_UNSAVED => None,
-            file_state => return Err(Error::new(
-                ErrorKind::Unsupported,
-                format!(
-                    "File state should be 1 or 0. There are likely {} bytes left in the buffer Remaining: {}",
-                    // When the file state is not 1 or 0 it indicates how many bytes are left in the
-                    // file
-                    file_state as usize + std::mem::size_of::<TabStateFooter>(),
-                    // This includes the header, but also includes the file state byte, so we add one
-                    // to the length of the remaining bytes
-                    br.len() + 1
-                ),
-            )),
+            // This includes the header, but also includes the file state byte, so we add one to the
+            // length of the remaining bytes
+            file_state => return Err(unsupported_state_error(file_state, br.len() + 1)),
         };
 
         // Check that the second unk in `TabStateMetaData` is 1 or not. If it isn't, we need to inspect
         // this file.
         if let Some(mdata) = metadata {
-            if *mdata.get_unk2() != METADATA_UNK2 {
-                return Err(Error::new(
-                        ErrorKind::InvalidData,
-                        format!(
-                            "Unknown value encountered for TabStateMetadata::unk2. Expected: 1 Got: {:02X?}.\n\
-                            Pleas inspect this file and help figure out the missing value means!",
-                            *mdata.get_unk2()
-                        ),
-                    ));
-            };
+            check_unk2(*mdata.get_unk2())?;
         };
 
         // After the first marker should be two more VarInt. These represent the cursor start and end
@@ -137,14 +217,9 @@ impl<'a> TabStateRefs<'a> {
         // sometimes is extra data. It might still be after the text buffer AND at the end of the file.
         let footer = br.read_t()?;
 
-        // Check that there are no bytes remaining in the buffer. If there are, print out how many.
-        if !br.is_empty() {
-            eprintln!(
-                "Please report on GH issues: Bytes still remaining in the buffer: {}",
-                br.len(),
-            );
-            eprintln!("Please send me your buffer file, as well, so I can see what is wrong!")
-        }
+        // Anything after the footer should be edit chunks, which Notepad appends while the tab is still
+        // open.
+        let (chunks, trailing) = read_chunks(br.read_bytes(br.len())?);
 
         Ok(TabStateRefs::new(
             header,
@@ -153,6 +228,56 @@ impl<'a> TabStateRefs<'a> {
             options,
             text_buffer,
             footer,
+            chunks,
+            trailing,
+            buffer,
         ))
     }
 }
+
+/// When the file state is not 1 or 0 it indicates how many bytes are left in the file.
+pub(crate) fn unsupported_state_error(file_state: u8, remaining: usize) -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        format!(
+            "File state should be 1 or 0. There are likely {} bytes left in the buffer Remaining: {}",
+            file_state as usize + core::mem::size_of::<TabStateFooter>(),
+            remaining
+        ),
+    )
+}
+
+/// Check that the second unk in `TabStateMetaData` is 1 or not. If it isn't, we need to inspect this
+/// file.
+pub(crate) fn check_unk2(unk2: u8) -> crate::error::Result<()> {
+    if unk2 != METADATA_UNK2 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Unknown value encountered for TabStateMetadata::unk2. Expected: 1 Got: {unk2:02X?}.\n\
+                Please run `tabstate bug-report` on this file and open an issue, so we can figure out \
+                what the value means!",
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reads edit chunks until the buffer runs out, or until something that isn't a valid chunk is found.
+/// Returns the chunks and the bytes that could not be read as chunks.
+fn read_chunks(buffer: &[u8]) -> (Vec<EditChunkRef<'_>>, &[u8]) {
+    let mut br = BufferReader::new(buffer);
+    let mut chunks = vec![];
+    let mut remaining = br.len();
+
+    while !br.is_empty() {
+        match EditChunkRef::from_reader(&mut br) {
+            Ok(chunk) => chunks.push(chunk),
+            Err(_) => break,
+        }
+        remaining = br.len();
+    }
+
+    (chunks, &buffer[buffer.len() - remaining..])
+}
@@ -1,6 +1,8 @@
-use crate::consts::SIGN_BIT;
-use crate::varint::VarInt;
-use buffer_reader::BufferReader;
+use crate::consts::{MAX_VARINT_LEN, SIGN_BIT};
+use crate::error::{Error, ErrorKind};
+use crate::reader::BufferReader;
+use crate::varint::{decode_bounded, decode_validated, to_usize, VarInt};
+use alloc::format;
 
 /// A reference to a slice of bytes that represent a variable sized integer.
 #[derive(Copy, Clone, PartialEq)]
@@ -12,7 +14,7 @@ impl<'a> VarIntRef<'a> {
     /// Assumes the provided buffer is only the varint bytes. Returns an error if the provided buffer
     /// is invalid, which includes empty buffer, leading bytes not being signed, or last byte being
     /// signed
-    pub fn new(buffer: &'a [u8]) -> std::io::Result<Self> {
+    pub fn new(buffer: &'a [u8]) -> crate::error::Result<Self> {
         Ok(Self {
             buffer: crate::varint::validate_buffer(buffer)?,
         })
@@ -21,19 +23,38 @@ impl<'a> VarIntRef<'a> {
     ///
     /// # Safety
     ///
-    /// Does not check that the buffer is valid.
+    /// Does not check that the buffer is valid. It must pass `varint::validate_buffer`.
     #[inline(always)]
     pub unsafe fn new_unchecked(buffer: &'a [u8]) -> Self {
         Self { buffer }
     }
     /// Assumes the reader is at the start of a varint. Reads the sign bit of each byte and advances
-    /// until the end of the varint and passes back a reference to the bytes as a `VarIntRef`
-    pub fn from_reader(br: &mut BufferReader<'a>) -> std::io::Result<Self> {
-        // Get the bytes that represent the size of the text buffer and decode the size. Buffer must
-        // be at least size 1, so we start the count at 1, and only increment if the sign bit is set.
+    /// until the end of the varint and passes back a reference to the bytes as a `VarIntRef`. The
+    /// value has to fit in a `usize`, so `decode` never has to clamp it.
+    pub fn from_reader(br: &mut BufferReader<'a>) -> crate::error::Result<Self> {
+        Self::from_reader_bounded(br, MAX_VARINT_LEN, usize::BITS)
+    }
+    /// Same as `from_reader`, but the varint can be at most `max_len` bytes, and the value has to fit in
+    /// `max_bits` bits. Returns an `ErrorKind::Overflow` error otherwise.
+    pub fn from_reader_bounded(
+        br: &mut BufferReader<'a>,
+        max_len: usize,
+        max_bits: u32,
+    ) -> crate::error::Result<Self> {
+        let max_len = max_len.min(MAX_VARINT_LEN);
+        // Get the bytes that represent the varint. Buffer must be at least size 1, so we start the
+        // count at 0, and keep going while the sign bit is set. We stop at `max_len`, so a long run of
+        // signed bytes can't keep us scanning.
         let mut count = 0;
 
         loop {
+            if count == max_len {
+                return Err(Error::new(
+                    ErrorKind::Overflow,
+                    format!("Varint is longer than the {max_len} bytes allowed"),
+                ));
+            }
+
             let byte = br.peek_byte(count)?;
             count += 1;
 
@@ -42,19 +63,37 @@ impl<'a> VarIntRef<'a> {
             }
         }
 
-        Ok(Self {
-            buffer: br.read_bytes(count)?,
-        })
+        let buffer = br.read_bytes(count)?;
+        decode_bounded(buffer, max_len, max_bits)?;
+
+        Ok(Self { buffer })
     }
-    /// Decodes a varint from the provided bytes
+    /// Decodes a varint from the provided bytes. Values that don't fit in a `usize` are clamped to
+    /// `usize::MAX`. That can't happen for varints read with `from_reader`, but use `try_decode` if you
+    /// made this varint some other way.
     #[inline(always)]
     pub fn decode(&self) -> usize {
-        crate::varint::decode(self.buffer) as usize
+        self.try_decode().unwrap_or(usize::MAX)
+    }
+    /// Decodes the varint as a `usize`, or returns an `ErrorKind::Overflow` error if it doesn't fit.
+    pub fn try_decode(&self) -> crate::error::Result<usize> {
+        to_usize(self.decode_lossless())
     }
     /// Decodes a varint from the provided bytes. Uses the largest int primitive available.
     #[inline(always)]
     pub fn decode_lossless(&self) -> u128 {
-        crate::varint::decode(self.buffer)
+        decode_validated(self.buffer)
+    }
+    /// Same as `new`, but also rejects varints that are not in their shortest form.
+    pub fn new_canonical(buffer: &'a [u8]) -> crate::error::Result<Self> {
+        Ok(Self {
+            buffer: crate::varint::validate_canonical_buffer(buffer)?,
+        })
+    }
+    /// Returns false if the varint uses more bytes than it needs to, like `80 00` for 0. See
+    /// `varint::is_canonical`.
+    pub fn is_canonical(&self) -> bool {
+        crate::varint::is_canonical(self.buffer)
     }
     #[inline(always)]
     pub fn get_buffer(&self) -> &[u8] {
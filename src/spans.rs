@@ -0,0 +1,496 @@
+#![doc = "Maps every byte of a TabState file to the field it belongs to. Useful for figuring out what the unknown bytes in new samples are."]
+
+use crate::consts::{FILE_STATE_SAVED, FILE_STATE_UNSAVED};
+use crate::enums::{CarriageType, Encoding};
+use crate::filetime::{filetime_to_unix_micros, format_iso8601};
+use crate::options::OPTIONS_SIZE;
+use crate::reader::{offset_of, BufferReader};
+use crate::refs::tabstate::buffer::decode_utf16le;
+use crate::refs::tabstate::TabStateRefs;
+use crate::refs::varint::VarIntRef;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum SpanKind {
+    /// A field we know the meaning of.
+    Field,
+    /// A field we parse, but don't know the meaning of, like `TabStateMetadata::unk`.
+    Unknown,
+    /// Bytes that no field covers.
+    Uncovered,
+}
+
+/// A range of bytes in a TabState file, and the field that they belong to.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldSpan {
+    pub offset: usize,
+    pub len: usize,
+    /// Path to the field, like `metadata.file_path.len` or `chunks[2].added_text`.
+    pub field: String,
+    /// The value of the field, for display. Empty for uncovered spans, so a span map never repeats
+    /// bytes that no field explains.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "String::is_empty"))]
+    pub value: String,
+    pub kind: SpanKind,
+}
+
+impl FieldSpan {
+    /// The offset one past the last byte of the span.
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+}
+
+/// Parses the buffer and returns a span for every field, in order. Any bytes not covered by a field
+/// get a `SpanKind::Uncovered` span.
+pub fn get_field_spans(buffer: &[u8]) -> crate::error::Result<Vec<FieldSpan>> {
+    let refs = TabStateRefs::from_buffer(buffer)?;
+    Ok(get_field_spans_from_refs(&refs))
+}
+
+/// Returns a span for every field in `refs`, in order, with offsets into the buffer `refs` was
+/// parsed from.
+pub fn get_field_spans_from_refs<'a>(refs: &'a TabStateRefs<'a>) -> Vec<FieldSpan> {
+    let mut spans = SpanBuilder::new(refs.get_raw_buffer());
+
+    let header = refs.get_header();
+    let magic = header.get_magic();
+    spans.push(
+        &magic[..2],
+        "header.magic",
+        format!("{:?}", String::from_utf8_lossy(&magic[..2])),
+        SpanKind::Field,
+    );
+    spans.push(
+        &magic[2..],
+        "header.magic[2]",
+        magic[2].to_string(),
+        SpanKind::Unknown,
+    );
+    spans.push_t(
+        &header.state,
+        "header.state",
+        header.get_state().to_string(),
+        SpanKind::Field,
+    );
+
+    if let Some(metadata) = refs.get_metadata() {
+        spans.push_varint(metadata.get_file_path_len(), "metadata.file_path.len");
+        spans.push_wide(metadata.get_raw_path(), "metadata.file_path");
+        spans.push_varint(metadata.get_full_buffer_size(), "metadata.full_buffer_size");
+        spans.push_t(
+            metadata.encoding,
+            "metadata.encoding",
+            format!("{:?}", metadata.encoding),
+            SpanKind::Field,
+        );
+        spans.push_t(
+            metadata.carriage_type,
+            "metadata.carriage_type",
+            format!("{:?}", metadata.carriage_type),
+            SpanKind::Field,
+        );
+        let filetime = metadata.filetime.decode_lossless() as u64;
+        spans.push(
+            metadata.filetime.get_buffer(),
+            "metadata.filetime",
+            format!(
+                "{filetime} ({})",
+                format_iso8601(filetime_to_unix_micros(filetime))
+            ),
+            SpanKind::Field,
+        );
+        spans.push(
+            &metadata.content_hash[..],
+            "metadata.content_hash",
+            to_hex(&metadata.content_hash[..]),
+            SpanKind::Field,
+        );
+        spans.push_t(
+            metadata.unk,
+            "metadata.unk",
+            metadata.unk.to_string(),
+            SpanKind::Unknown,
+        );
+        spans.push_t(
+            metadata.unk2,
+            "metadata.unk2",
+            metadata.unk2.to_string(),
+            SpanKind::Unknown,
+        );
+    }
+
+    spans.push_varint(refs.get_cursor_start(), "cursor.start");
+    spans.push_varint(refs.get_cursor_end(), "cursor.end");
+
+    let options = refs.get_options();
+    let option_bytes = bytes_of(options);
+    let option_fields = [
+        (
+            "options.word_wrap",
+            options.word_wrap().to_string(),
+            SpanKind::Field,
+        ),
+        (
+            "options.right_to_left",
+            options.right_to_left().to_string(),
+            SpanKind::Field,
+        ),
+        (
+            "options.show_unicode_control",
+            options.show_unicode_control().to_string(),
+            SpanKind::Field,
+        ),
+        (
+            "options.unk",
+            option_bytes[3].to_string(),
+            SpanKind::Unknown,
+        ),
+    ];
+    for (byte, (field, value, kind)) in option_bytes.iter().zip(option_fields) {
+        spans.push_t(byte, field, value, kind);
+    }
+
+    spans.push_varint(refs.get_buffer_len(), "text.len");
+    spans.push_wide(refs.get_raw_text_buffer(), "text");
+
+    let footer = refs.get_footer();
+    spans.push_t(
+        &footer.the_number_zero,
+        "footer.the_number_zero",
+        footer.the_number_zero.to_string(),
+        SpanKind::Unknown,
+    );
+    spans.push(
+        &footer.crc[..],
+        "footer.crc",
+        format!("{:08X}", footer.get_crc()),
+        SpanKind::Field,
+    );
+
+    for (i, chunk) in refs.get_chunks().iter().enumerate() {
+        spans.push_varint(chunk.get_position(), &format!("chunks[{i}].position"));
+        spans.push_varint(
+            chunk.get_deletion_count(),
+            &format!("chunks[{i}].deletion_count"),
+        );
+        spans.push_varint(
+            chunk.get_addition_count(),
+            &format!("chunks[{i}].addition_count"),
+        );
+        spans.push_wide(
+            chunk.get_raw_added_text(),
+            &format!("chunks[{i}].added_text"),
+        );
+        spans.push(
+            chunk.get_crc_bytes(),
+            &format!("chunks[{i}].crc"),
+            format!("{:08X}", chunk.get_crc()),
+            SpanKind::Field,
+        );
+    }
+
+    spans.finish()
+}
+
+/// Returns spans for what can be found without parsing the whole file: the header, the metadata, the
+/// cursor, the options and the text. Nothing is checked, so this works on files `get_field_spans`
+/// rejects, like ones with an unknown version, encoding or unk2. The fields are read with the layout
+/// we know, so for an unknown version they're only a guess. It stops after the header if the state
+/// isn't saved or unsaved, and at the first field that runs past the end of the buffer. Everything
+/// after that is uncovered.
+pub fn skim_field_spans(buffer: &[u8]) -> Vec<FieldSpan> {
+    let mut spans = SpanBuilder::new(buffer);
+    // Running out of buffer just means there's nothing more to skim.
+    let _ = skim(&mut spans, &mut BufferReader::new(buffer));
+    spans.finish()
+}
+
+fn skim<'b>(spans: &mut SpanBuilder<'b>, br: &mut BufferReader<'b>) -> crate::error::Result<()> {
+    let magic = br.read_bytes(2)?;
+    spans.push(
+        magic,
+        "header.magic",
+        format!("{:?}", String::from_utf8_lossy(magic)),
+        SpanKind::Field,
+    );
+    let version = br.read_bytes(1)?;
+    spans.push(
+        version,
+        "header.magic[2]",
+        version[0].to_string(),
+        SpanKind::Unknown,
+    );
+    let state = br.read_bytes(1)?;
+    spans.push(state, "header.state", state[0].to_string(), SpanKind::Field);
+
+    match state[0] {
+        FILE_STATE_SAVED => skim_metadata(spans, br)?,
+        FILE_STATE_UNSAVED => {}
+        _ => return Ok(()),
+    }
+
+    skim_varint(spans, br, "cursor.start")?;
+    skim_varint(spans, br, "cursor.end")?;
+    let options = br.read_bytes(OPTIONS_SIZE)?;
+    let option_fields = [
+        "options.word_wrap",
+        "options.right_to_left",
+        "options.show_unicode_control",
+    ];
+    for (i, field) in option_fields.into_iter().enumerate() {
+        let value = (options[i] != 0).to_string();
+        spans.push(&options[i..i + 1], field, value, SpanKind::Field);
+    }
+    spans.push(
+        &options[3..],
+        "options.unk",
+        options[3].to_string(),
+        SpanKind::Unknown,
+    );
+
+    let text_len = skim_varint(spans, br, "text.len")?;
+    spans.push_wide(br.read_bytes(text_len.saturating_mul(2))?, "text");
+
+    Ok(())
+}
+
+fn skim_metadata<'b>(
+    spans: &mut SpanBuilder<'b>,
+    br: &mut BufferReader<'b>,
+) -> crate::error::Result<()> {
+    let path_len = skim_varint(spans, br, "metadata.file_path.len")?;
+    spans.push_wide(
+        br.read_bytes(path_len.saturating_mul(2))?,
+        "metadata.file_path",
+    );
+    skim_varint(spans, br, "metadata.full_buffer_size")?;
+
+    let encoding = br.read_bytes(1)?;
+    let value = match Encoding::from_value(encoding[0]) {
+        Some(encoding) => format!("{encoding:?}"),
+        None => format!("unknown {}", encoding[0]),
+    };
+    spans.push(encoding, "metadata.encoding", value, SpanKind::Field);
+    let carriage_type = br.read_bytes(1)?;
+    let value = match CarriageType::from_value(carriage_type[0]) {
+        Some(carriage_type) => format!("{carriage_type:?}"),
+        None => format!("unknown {}", carriage_type[0]),
+    };
+    spans.push(
+        carriage_type,
+        "metadata.carriage_type",
+        value,
+        SpanKind::Field,
+    );
+
+    skim_varint(spans, br, "metadata.filetime")?;
+    let content_hash = br.read_bytes(0x20)?;
+    spans.push(
+        content_hash,
+        "metadata.content_hash",
+        to_hex(content_hash),
+        SpanKind::Field,
+    );
+    for field in ["metadata.unk", "metadata.unk2"] {
+        let byte = br.read_bytes(1)?;
+        spans.push(byte, field, byte[0].to_string(), SpanKind::Unknown);
+    }
+
+    Ok(())
+}
+
+/// Reads a varint, pushes its span, and returns its value.
+fn skim_varint<'b>(
+    spans: &mut SpanBuilder<'b>,
+    br: &mut BufferReader<'b>,
+    field: &str,
+) -> crate::error::Result<usize> {
+    let varint = VarIntRef::from_reader(br)?;
+    spans.push_varint(varint, field);
+    Ok(varint.decode())
+}
+
+/// Collects spans and works out their offsets from the references into the buffer.
+struct SpanBuilder<'b> {
+    buffer: &'b [u8],
+    spans: Vec<FieldSpan>,
+}
+
+impl<'b> SpanBuilder<'b> {
+    fn new(buffer: &'b [u8]) -> Self {
+        Self {
+            buffer,
+            spans: vec![],
+        }
+    }
+    fn push(&mut self, bytes: &[u8], field: &str, value: String, kind: SpanKind) {
+        self.spans.push(FieldSpan {
+            offset: offset_of(self.buffer, bytes),
+            len: bytes.len(),
+            field: field.to_string(),
+            value,
+            kind,
+        });
+    }
+    fn push_t<T: bytemuck::AnyBitPattern>(
+        &mut self,
+        t: &T,
+        field: &str,
+        value: String,
+        kind: SpanKind,
+    ) {
+        self.push(bytes_of(t), field, value, kind);
+    }
+    fn push_varint(&mut self, varint: VarIntRef, field: &str) {
+        self.push(
+            varint.get_buffer(),
+            field,
+            varint.decode_lossless().to_string(),
+            SpanKind::Field,
+        );
+    }
+    fn push_wide(&mut self, bytes: &[u8], field: &str) {
+        self.push(
+            bytes,
+            field,
+            format!("{:?}", decode_utf16le(bytes).to_string_lossy()),
+            SpanKind::Field,
+        );
+    }
+    /// Sorts the spans and fills in any gaps with `SpanKind::Uncovered` spans.
+    fn finish(mut self) -> Vec<FieldSpan> {
+        self.spans.sort_by_key(|s| s.offset);
+
+        let mut spans = Vec::with_capacity(self.spans.len());
+        let mut covered = 0;
+        for span in self.spans {
+            if span.offset > covered {
+                spans.push(uncovered(covered, span.offset));
+            }
+            covered = covered.max(span.end());
+            spans.push(span);
+        }
+        if covered < self.buffer.len() {
+            spans.push(uncovered(covered, self.buffer.len()));
+        }
+
+        spans
+    }
+}
+
+fn uncovered(start: usize, end: usize) -> FieldSpan {
+    FieldSpan {
+        offset: start,
+        len: end - start,
+        field: "uncovered".to_string(),
+        value: String::new(),
+        kind: SpanKind::Uncovered,
+    }
+}
+
+/// Gets the bytes of a type that was read out of the buffer.
+pub(crate) fn bytes_of<T: bytemuck::AnyBitPattern>(t: &T) -> &[u8] {
+    // `AnyBitPattern` types that we read from the buffer have no padding, so every byte is initialized.
+    unsafe { core::slice::from_raw_parts(t as *const T as *const u8, core::mem::size_of::<T>()) }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02X}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spans::{get_field_spans, skim_field_spans, FieldSpan, SpanKind};
+    use crate::test_util::saved_tab;
+
+    /// Every byte should be in exactly one span, in order.
+    #[test]
+    fn spans_cover_buffer() {
+        let buffer = saved_tab().to_bytes();
+        let spans = get_field_spans(&buffer[..]).unwrap();
+
+        let mut offset = 0;
+        for span in &spans {
+            assert_eq!(span.offset, offset, "{span:?}");
+            offset = span.end();
+        }
+        assert_eq!(offset, buffer.len());
+        assert!(spans.iter().all(|s| s.kind != SpanKind::Uncovered));
+
+        let unknown = spans
+            .iter()
+            .filter(|s| s.kind == SpanKind::Unknown)
+            .map(|s| s.field.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unknown,
+            [
+                "header.magic[2]",
+                "metadata.unk",
+                "metadata.unk2",
+                "options.unk",
+                "footer.the_number_zero"
+            ]
+        );
+    }
+
+    /// Trailing bytes that aren't edit chunks should be flagged.
+    #[test]
+    fn trailing_bytes_are_uncovered() {
+        let mut buffer = saved_tab().to_bytes();
+        buffer.push(0x80);
+
+        let spans = get_field_spans(&buffer[..]).unwrap();
+        let last = spans.last().unwrap();
+        assert_eq!(last.kind, SpanKind::Uncovered);
+        assert_eq!((last.offset, last.len), (buffer.len() - 1, 1));
+    }
+
+    /// Skimming should find the same fields as parsing, up to the text, and keep going when parsing
+    /// fails.
+    #[test]
+    fn skims_unparsable_files() {
+        let layout = |spans: &[FieldSpan]| {
+            spans
+                .iter()
+                .map(|s| (s.offset, s.len, s.field.clone(), s.kind))
+                .collect::<Vec<_>>()
+        };
+        let bytes = saved_tab().to_bytes();
+        let spans = get_field_spans(&bytes).unwrap();
+        let skimmed = skim_field_spans(&bytes);
+        let text = spans.iter().position(|s| s.field == "text").unwrap();
+        assert_eq!(layout(&skimmed[..=text]), layout(&spans[..=text]));
+        assert_eq!(skimmed.len(), text + 2);
+        assert_eq!(skimmed[text + 1].kind, SpanKind::Uncovered);
+        assert_eq!(skimmed[text + 1].end(), bytes.len());
+        assert_eq!(skimmed[text + 1].value, "");
+
+        let mut tab = saved_tab();
+        tab.metadata.as_mut().unwrap().unk2 = 7;
+        let bytes = tab.to_bytes();
+        assert!(get_field_spans(&bytes).is_err());
+        let skimmed = skim_field_spans(&bytes);
+        let unk2 = skimmed.iter().find(|s| s.field == "metadata.unk2").unwrap();
+        assert_eq!(unk2.value, "7");
+
+        // An unknown state stops after the header.
+        let skimmed = skim_field_spans(b"NP\x00\x05secret");
+        let fields = skimmed.iter().map(|s| s.field.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            [
+                "header.magic",
+                "header.magic[2]",
+                "header.state",
+                "uncovered"
+            ]
+        );
+    }
+}
@@ -0,0 +1,268 @@
+#![doc = "Statistics over the fields we don't know the meaning of yet. Feed it every file you have, and it counts each value the unknown fields take, and what the rest of the file looked like when they did, so we can guess what they are for."]
+
+use crate::spans::skim_field_spans;
+use crate::tabstate::TabState;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+
+/// The fields that are still undecoded, named the same way as in `spans`.
+pub const UNKNOWN_FIELDS: [&str; 5] = [
+    "header.unk",
+    "metadata.unk",
+    "metadata.unk2",
+    "options.unk",
+    "footer.the_number_zero",
+];
+
+/// The known properties each unknown value is correlated with.
+pub const PROPERTIES: [&str; 8] = [
+    "state",
+    "encoding",
+    "carriage_type",
+    "options.word_wrap",
+    "options.right_to_left",
+    "options.show_unicode_control",
+    "text.len",
+    "chunks",
+];
+
+/// How often one value of an unknown field was seen, and what the known properties of those files
+/// were.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ValueStats {
+    pub count: usize,
+    /// Property name -> property value -> the amount of files with this value of the unknown field
+    /// that had that property value.
+    pub properties: BTreeMap<&'static str, BTreeMap<String, usize>>,
+}
+
+/// Every value one unknown field was seen with.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FieldStats {
+    pub values: BTreeMap<u8, ValueStats>,
+}
+
+/// Stats for a set of TabState files. Add each file with `add`, each file that failed to parse with
+/// `add_unparsed`, and each file that couldn't even be read with `add_failed`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnknownStats {
+    /// The amount of files that were parsed.
+    pub files: usize,
+    /// The amount of files that could not be parsed.
+    pub failed: usize,
+    /// Field name -> stats. Only has the fields in `UNKNOWN_FIELDS`. The metadata fields only count
+    /// saved files.
+    pub fields: BTreeMap<&'static str, FieldStats>,
+    /// The same as `fields`, but for the files added with `add_unparsed`. This is where values that
+    /// make the parser error (like `metadata.unk2`) show up. The values are read with
+    /// `skim_field_spans`, so a file only counts for the fields the skim got to, and only has the
+    /// properties the skim found. `chunks` and `footer.the_number_zero` are never skimmed.
+    pub unparsed: BTreeMap<&'static str, FieldStats>,
+}
+
+impl UnknownStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add(&mut self, tab: &TabState) {
+        self.files += 1;
+
+        let properties = get_properties(tab).map(Some);
+        count(&mut self.fields, get_unknown_values(tab), &properties);
+    }
+    /// Adds a file that failed to parse, and counts whatever unknown values can still be read from it.
+    pub fn add_unparsed(&mut self, buffer: &[u8]) {
+        self.failed += 1;
+
+        let spans = skim_field_spans(buffer);
+        let value = |field: &str| {
+            spans
+                .iter()
+                .find(|span| span.field == field)
+                .map(|span| span.value.as_str())
+        };
+        // The version byte is `header.unk` here, but `header.magic[2]` in the spans.
+        let values = [
+            "header.magic[2]",
+            "metadata.unk",
+            "metadata.unk2",
+            "options.unk",
+            "footer.the_number_zero",
+        ]
+        .map(|field| value(field).and_then(|v| v.parse().ok()));
+        let properties = [
+            value("header.state").map(|state| {
+                match state {
+                    "1" => "saved",
+                    "0" => "unsaved",
+                    _ => "unknown",
+                }
+                .to_string()
+            }),
+            value("metadata.encoding").map(ToString::to_string),
+            value("metadata.carriage_type").map(ToString::to_string),
+            value("options.word_wrap").map(ToString::to_string),
+            value("options.right_to_left").map(ToString::to_string),
+            value("options.show_unicode_control").map(ToString::to_string),
+            value("text.len")
+                .and_then(|len| len.parse().ok())
+                .map(|len| text_len_bucket(len).to_string()),
+            None,
+        ];
+        count(&mut self.unparsed, values, &properties);
+    }
+    /// Adds a file that couldn't be read at all.
+    pub fn add_failed(&mut self) {
+        self.failed += 1;
+    }
+}
+
+/// Counts each value in `values`, which are in the order of `UNKNOWN_FIELDS`, along with the
+/// properties, which are in the order of `PROPERTIES`. `None`s are skipped.
+fn count(
+    fields: &mut BTreeMap<&'static str, FieldStats>,
+    values: [Option<u8>; 5],
+    properties: &[Option<String>; 8],
+) {
+    for (field, value) in UNKNOWN_FIELDS.into_iter().zip(values) {
+        let Some(value) = value else {
+            continue;
+        };
+        let stats = fields
+            .entry(field)
+            .or_default()
+            .values
+            .entry(value)
+            .or_default();
+        stats.count += 1;
+        for (property, property_value) in PROPERTIES.into_iter().zip(properties) {
+            let Some(property_value) = property_value else {
+                continue;
+            };
+            *stats
+                .properties
+                .entry(property)
+                .or_default()
+                .entry(property_value.clone())
+                .or_default() += 1;
+        }
+    }
+}
+
+/// The raw value of each field in `UNKNOWN_FIELDS`, in the same order. The metadata fields are
+/// `None` for unsaved files.
+pub fn get_unknown_values(tab: &TabState) -> [Option<u8>; 5] {
+    [
+        Some(tab.header_unk),
+        tab.metadata.as_ref().map(|m| m.unk),
+        tab.metadata.as_ref().map(|m| m.unk2),
+        Some(tab.options.as_bytes()[3]),
+        Some(tab.the_number_zero),
+    ]
+}
+
+/// The value of each property in `PROPERTIES`, in the same order.
+fn get_properties(tab: &TabState) -> [String; 8] {
+    let saved = |f: fn(&crate::tabstate::metadata::Metadata) -> String| {
+        tab.metadata.as_ref().map_or("unsaved".to_string(), f)
+    };
+    [
+        match tab.metadata {
+            Some(_) => "saved",
+            None => "unsaved",
+        }
+        .to_string(),
+        saved(|m| alloc::format!("{:?}", m.encoding)),
+        saved(|m| alloc::format!("{:?}", m.carriage_type)),
+        tab.options.word_wrap().to_string(),
+        tab.options.right_to_left().to_string(),
+        tab.options.show_unicode_control().to_string(),
+        text_len_bucket(tab.text.encode_utf16().count()).to_string(),
+        match tab.chunks.len() {
+            0 => "none",
+            _ => "some",
+        }
+        .to_string(),
+    ]
+}
+
+/// Groups text lengths by how many varint bytes they take, so the correlation tables stay small.
+fn text_len_bucket(len: usize) -> &'static str {
+    match len {
+        0 => "0",
+        1..=0x7F => "1-127",
+        0x80..=0x3FFF => "128-16383",
+        _ => "16384+",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::options::TabStateOptions;
+    use crate::stats::UnknownStats;
+    use crate::tabstate::TabState;
+    use crate::test_util::{saved_tab, unsaved_tab};
+
+    fn saved() -> TabState {
+        TabState {
+            options: TabStateOptions::new(1, 0, 0, 0),
+            ..saved_tab()
+        }
+    }
+
+    #[test]
+    fn counts_values_and_properties() {
+        let mut stats = UnknownStats::new();
+        stats.add(&unsaved_tab());
+        stats.add(&saved());
+        stats.add(&saved());
+        stats.add_failed();
+
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.failed, 1);
+
+        // Unsaved files have no metadata, so they aren't counted for it.
+        let unk2 = &stats.fields["metadata.unk2"].values;
+        assert_eq!(unk2.len(), 1);
+        assert_eq!(unk2[&1].count, 2);
+
+        let options_unk = &stats.fields["options.unk"].values;
+        assert_eq!(options_unk[&0].count, 2);
+        assert_eq!(options_unk[&1].count, 1);
+        assert_eq!(options_unk[&1].properties["state"]["unsaved"], 1);
+        assert_eq!(options_unk[&1].properties["chunks"]["some"], 1);
+        assert_eq!(options_unk[&0].properties["encoding"]["UTF8"], 2);
+        assert_eq!(options_unk[&0].properties["text.len"]["1-127"], 2);
+    }
+
+    #[test]
+    fn counts_unparsed_values() {
+        let mut buffer = saved().to_bytes();
+        // The content hash is right before unk and unk2.
+        let unk2 = buffer
+            .windows(0x20)
+            .position(|w| w == [0xAB; 0x20])
+            .unwrap()
+            + 0x21;
+        buffer[unk2] = 7;
+        assert!(TabState::from_buffer(&buffer).is_err());
+
+        let mut stats = UnknownStats::new();
+        stats.add(&saved());
+        stats.add_unparsed(&buffer);
+
+        assert_eq!(stats.files, 1);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.fields["metadata.unk2"].values.len(), 1);
+
+        let unk2 = &stats.unparsed["metadata.unk2"].values[&7];
+        assert_eq!(unk2.count, 1);
+        assert_eq!(unk2.properties["state"]["saved"], 1);
+        assert_eq!(unk2.properties["carriage_type"]["CRLF"], 1);
+        assert!(!unk2.properties.contains_key("chunks"));
+        assert!(!stats.unparsed.contains_key("footer.the_number_zero"));
+    }
+}
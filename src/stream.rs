@@ -0,0 +1,421 @@
+#![doc = "Parses a TabState file into an owned `TabState` straight from a `Read` source, without reading the whole file into memory first."]
+
+use crate::anomaly::{non_canonical_varint, Anomaly, AnomalyKind};
+use crate::consts::{FILE_STATE_SAVED, FILE_STATE_UNSAVED, MAX_VARINT_LEN, SIGN_BIT};
+use crate::enums::{CarriageType, Encoding};
+use crate::options::TabStateOptions;
+use crate::refs::tabstate::buffer::utf16le_chars;
+use crate::refs::tabstate::metadata::{check_carriage_type, check_encoding};
+use crate::refs::tabstate::{check_unk2, unsupported_state_error};
+use crate::refs::varint::VarIntRef;
+use crate::tabstate::chunk::EditChunk;
+use crate::tabstate::metadata::Metadata;
+use crate::tabstate::TabState;
+use crate::varint::decode_bounded;
+use crate::version::{version_from_magic, FormatVersion};
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+
+/// Whether the error is about what was read, rather than the reader failing. The stream running out
+/// counts, since that's how a half written chunk looks.
+fn is_parse_error(error: &Error) -> bool {
+    error.kind() == ErrorKind::UnexpectedEof
+        || error
+            .get_ref()
+            .is_some_and(|e| e.is::<crate::error::Error>())
+}
+
+/// Text buffers are read this many chars at a time. A length varint can say whatever it wants, but
+/// this way we only ever allocate as much as the stream actually has.
+pub const TEXT_READ_SIZE: usize = 0x10000;
+
+/// Parses a TabState file from any `Read` source.
+pub struct TabStateStreamParser<R> {
+    reader: R,
+    /// Bytes left in the stream, if we know it. This is only known for `Read + Seek` sources.
+    remaining: Option<u64>,
+    /// Bytes read so far.
+    position: u64,
+    /// Only trailing bytes and padded varints are reported here. Use `TabStateRefs` if you need the rest
+    /// of the checks.
+    anomalies: Vec<Anomaly>,
+}
+
+impl<R: Read> TabStateStreamParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            remaining: None,
+            position: 0,
+            anomalies: vec![],
+        }
+    }
+    /// Reads the whole TabState file, including any edit chunks, until the end of the stream.
+    pub fn parse(self) -> std::io::Result<TabState> {
+        Ok(self.parse_with_anomalies()?.0)
+    }
+    /// Same as `parse`, but also returns anything unexpected that was found along the way.
+    pub fn parse_with_anomalies(mut self) -> std::io::Result<(TabState, Vec<Anomaly>)> {
+        let magic = self.read_array::<3>()?;
+        match version_from_magic(&magic)? {
+            FormatVersion::V1 => {}
+        }
+
+        let metadata = match self.read_u8()? {
+            FILE_STATE_SAVED => Some(self.read_metadata()?),
+            FILE_STATE_UNSAVED => None,
+            file_state => {
+                let remaining = self.remaining.map_or(0, |r| r as usize);
+                return Err(unsupported_state_error(file_state, remaining + 1).into());
+            }
+        };
+
+        let cursor_start = self.read_varint("cursor.start")?;
+        let cursor_end = self.read_varint("cursor.end")?;
+        let [word_wrap, right_to_left, show_unicode_control, unk] = self.read_array::<4>()?;
+
+        let text_len = self.read_varint("text.len")?;
+        let text = self.read_wide(text_len)?;
+
+        let the_number_zero = self.read_u8()?;
+        let crc = u32::from_be_bytes(self.read_array::<4>()?);
+
+        let chunks = self.read_chunks()?;
+
+        let tab = TabState {
+            header_unk: magic[2],
+            metadata,
+            cursor_start,
+            cursor_end,
+            options: TabStateOptions::new(word_wrap, right_to_left, show_unicode_control, unk),
+            text,
+            text_len: Some(text_len),
+            the_number_zero,
+            crc: Some(crc),
+            chunks,
+        };
+
+        Ok((tab, self.anomalies))
+    }
+    fn read_metadata(&mut self) -> std::io::Result<Metadata> {
+        let path_len = self.read_varint("metadata.file_path.len")?;
+        let path = self.read_wide(path_len)?;
+        let full_buffer_size = self.read_varint("metadata.full_buffer_size")?;
+
+        let encoding = self.read_u8()?;
+        check_encoding(encoding)?;
+        let carriage_type = self.read_u8()?;
+        check_carriage_type(carriage_type)?;
+
+        let first = self.read_u8()?;
+        let filetime = self.read_varint_from(first, u64::BITS, "metadata.filetime")? as u64;
+        let content_hash = self.read_array::<0x20>()?;
+        let unk = self.read_u8()?;
+        let unk2 = self.read_u8()?;
+        check_unk2(unk2)?;
+
+        Ok(Metadata {
+            path,
+            path_len: Some(path_len),
+            full_buffer_size: Some(full_buffer_size),
+            // These were checked above.
+            encoding: Encoding::from_value(encoding).unwrap(),
+            carriage_type: CarriageType::from_value(carriage_type).unwrap(),
+            filetime,
+            content_hash,
+            unk,
+            unk2,
+        })
+    }
+    /// Reads edit chunks until the end of the stream. If a chunk can't be read, because the stream
+    /// ends in the middle of it or it has a bad varint, the chunks up to that point are returned and
+    /// the rest is trailing bytes, same as `TabStateRefs::from_buffer`. Errors from the reader itself
+    /// are still returned.
+    fn read_chunks(&mut self) -> std::io::Result<Vec<EditChunk>> {
+        let mut chunks = vec![];
+
+        loop {
+            let start = self.position;
+            let Some(first) = self.read_first_byte()? else {
+                return Ok(chunks);
+            };
+            let anomaly_count = self.anomalies.len();
+
+            match self.read_chunk(chunks.len(), first) {
+                Ok(chunk) => chunks.push(chunk),
+                Err(e) if is_parse_error(&e) => {
+                    // `TabStateRefs` doesn't report padded varints in the trailing bytes either.
+                    self.anomalies.truncate(anomaly_count);
+                    let remaining = self.position - start
+                        + std::io::copy(&mut self.reader, &mut std::io::sink())?;
+                    self.anomalies.push(Anomaly {
+                        kind: AnomalyKind::TrailingBytes,
+                        offset: start as usize,
+                        len: remaining as usize,
+                        field: "trailing".to_string(),
+                        message: format!("{remaining} bytes after the last edit chunk"),
+                    });
+                    return Ok(chunks);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    fn read_chunk(&mut self, i: usize, first: u8) -> std::io::Result<EditChunk> {
+        let position = self.read_usize_varint_from(first, &format!("chunks[{i}].position"))?;
+        let deletion_count = self.read_varint(&format!("chunks[{i}].deletion_count"))?;
+        let addition_count = self.read_varint(&format!("chunks[{i}].addition_count"))?;
+        let added_text = self.read_wide(addition_count)?;
+        let crc = u32::from_be_bytes(self.read_array::<4>()?);
+
+        Ok(EditChunk {
+            position,
+            deletion_count,
+            addition_count: Some(addition_count),
+            added_text,
+            crc: Some(crc),
+        })
+    }
+    fn read_exact(&mut self, buffer: &mut [u8]) -> std::io::Result<()> {
+        self.reader.read_exact(buffer)?;
+        self.position += buffer.len() as u64;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(buffer.len() as u64);
+        }
+        Ok(())
+    }
+    fn read_array<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let mut buffer = [0; N];
+        self.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+    fn read_u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.read_array::<1>()?[0])
+    }
+    /// Reads a single byte, or returns `None` if the stream is at the end.
+    fn read_first_byte(&mut self) -> std::io::Result<Option<u8>> {
+        let mut buffer = [0; 1];
+        loop {
+            match self.reader.read(&mut buffer) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.position += 1;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(1);
+        }
+        Ok(Some(buffer[0]))
+    }
+    /// Reads a varint for `field`. `field` is only used to name the anomaly if the varint is padded.
+    fn read_varint(&mut self, field: &str) -> std::io::Result<usize> {
+        let first = self.read_u8()?;
+        self.read_usize_varint_from(first, field)
+    }
+    fn read_usize_varint_from(&mut self, first: u8, field: &str) -> std::io::Result<usize> {
+        // Bounded to `usize::BITS`, so this can't truncate.
+        Ok(self.read_varint_from(first, usize::BITS, field)? as usize)
+    }
+    /// Reads the rest of a varint that starts with `first`. Stops after `MAX_VARINT_LEN` bytes, so a
+    /// stream of signed bytes can't keep us reading.
+    fn read_varint_from(&mut self, first: u8, max_bits: u32, field: &str) -> std::io::Result<u128> {
+        let mut buffer = vec![first];
+        while buffer[buffer.len() - 1] & SIGN_BIT != 0 {
+            if buffer.len() == MAX_VARINT_LEN {
+                return Err(crate::error::Error::new(
+                    crate::error::ErrorKind::Overflow,
+                    format!("Varint is longer than the {MAX_VARINT_LEN} bytes allowed"),
+                )
+                .into());
+            }
+            buffer.push(self.read_u8()?);
+        }
+
+        let value = decode_bounded(&buffer[..], MAX_VARINT_LEN, max_bits)?;
+        let varint = VarIntRef::new(&buffer[..])?;
+        if !varint.is_canonical() {
+            let offset = (self.position - buffer.len() as u64) as usize;
+            self.anomalies
+                .push(non_canonical_varint(offset, varint, field));
+        }
+
+        Ok(value)
+    }
+    /// Reads `len` UTF-16LE chars, `TEXT_READ_SIZE` chars at a time.
+    fn read_wide(&mut self, len: usize) -> std::io::Result<String> {
+        if let Some(remaining) = self.remaining {
+            if len as u128 * 2 > remaining as u128 {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!(
+                        "Text buffer is {len} chars, but there are only {remaining} bytes left in the stream"
+                    ),
+                ));
+            }
+        }
+
+        let mut chars = Vec::with_capacity(len.min(TEXT_READ_SIZE));
+        let mut bytes = vec![0; len.min(TEXT_READ_SIZE) * 2];
+        let mut left = len;
+        while left > 0 {
+            let read = left.min(TEXT_READ_SIZE);
+            self.read_exact(&mut bytes[..read * 2])?;
+            chars.extend_from_slice(&utf16le_chars(&bytes[..read * 2]));
+            left -= read;
+        }
+
+        Ok(String::from_utf16_lossy(&chars[..]))
+    }
+}
+
+impl<R: Read + Seek> TabStateStreamParser<R> {
+    /// Creates a parser that knows how many bytes are left in the stream, so it can reject lengths that
+    /// are larger than the rest of the file before reading anything.
+    pub fn new_seekable(mut reader: R) -> std::io::Result<Self> {
+        let start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+
+        Ok(Self {
+            reader,
+            remaining: Some(end.saturating_sub(start)),
+            position: 0,
+            anomalies: vec![],
+        })
+    }
+}
+
+impl TabState {
+    /// Parses a TabState file from any `Read` source.
+    pub fn from_reader<R: Read>(reader: R) -> std::io::Result<Self> {
+        TabStateStreamParser::new(reader).parse()
+    }
+    /// Parses a TabState file from a `Read + Seek` source. Lengths that are larger than the rest of the
+    /// stream are rejected up front.
+    pub fn from_seekable_reader<R: Read + Seek>(reader: R) -> std::io::Result<Self> {
+        TabStateStreamParser::new_seekable(reader)?.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enums::{CarriageType, Encoding};
+    use crate::options::TabStateOptions;
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::stream::TabStateStreamParser;
+    use crate::tabstate::chunk::EditChunk;
+    use crate::tabstate::TabState;
+    use crate::test_util::saved_tab;
+    use std::io::{Cursor, ErrorKind};
+
+    fn tab() -> TabState {
+        let mut tab = TabState {
+            cursor_start: 1,
+            cursor_end: 1,
+            options: TabStateOptions::new(0, 0, 1, 1),
+            text: "a".repeat(super::TEXT_READ_SIZE + 10),
+            chunks: vec![EditChunk {
+                position: 0,
+                deletion_count: 1,
+                addition_count: None,
+                added_text: "b".to_string(),
+                crc: None,
+            }],
+            ..saved_tab()
+        };
+        let metadata = tab.metadata.as_mut().unwrap();
+        metadata.encoding = Encoding::UTF16LE;
+        metadata.carriage_type = CarriageType::Unix;
+        tab
+    }
+
+    /// The stream parser should give the same result as parsing the buffer.
+    #[test]
+    fn matches_buffer_parser() {
+        let bytes = tab().to_bytes();
+        let expected = TabState::from_buffer(&bytes[..]).unwrap();
+
+        assert_eq!(TabState::from_reader(&bytes[..]).unwrap(), expected);
+        assert_eq!(
+            TabState::from_seekable_reader(Cursor::new(&bytes)).unwrap(),
+            expected
+        );
+    }
+
+    /// Trailing bytes should be reported the same way `TabStateRefs` reports them.
+    #[test]
+    fn reports_trailing_bytes() {
+        let mut bytes = tab().to_bytes();
+        bytes.extend_from_slice(&[0x01, 0x00]);
+        let refs = TabStateRefs::from_buffer(&bytes[..]).unwrap();
+
+        let (_, anomalies) = TabStateStreamParser::new(&bytes[..])
+            .parse_with_anomalies()
+            .unwrap();
+        assert_eq!(anomalies, refs.get_anomalies());
+    }
+
+    /// Chunks that can't be read are trailing bytes for both parsers, whatever is wrong with them, and
+    /// padded varints in them aren't reported.
+    #[test]
+    fn bad_chunks_are_trailing_bytes() {
+        let trailing: [&[u8]; 4] = [
+            &[0x80],
+            &[0xFF; 30],
+            // A padded position, then a deletion count that doesn't fit in a usize.
+            &[
+                0x85, 0x80, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F,
+            ],
+            // A padded position, then the stream ends.
+            &[0x85, 0x80, 0x00, 0x01],
+        ];
+        for trailing in trailing {
+            let mut bytes = tab().to_bytes();
+            bytes.extend_from_slice(trailing);
+
+            let refs = TabStateRefs::from_buffer(&bytes[..]).unwrap();
+            let (parsed, anomalies) = TabStateStreamParser::new(&bytes[..])
+                .parse_with_anomalies()
+                .unwrap();
+            assert_eq!(parsed, refs.to_tab_state(), "{trailing:02X?}");
+            assert_eq!(anomalies, refs.get_anomalies(), "{trailing:02X?}");
+            assert_eq!(anomalies.len(), 1);
+            assert_eq!(anomalies[0].len, trailing.len());
+        }
+    }
+
+    /// Padded varints should be reported with the same field names and offsets as `TabStateRefs`.
+    #[test]
+    fn reports_padded_varints() {
+        let mut bytes = tab().to_bytes();
+        // The chunk position is the first byte after the footer. Pad it to three bytes.
+        let position = bytes.len() - 1 - 1 - 1 - 2 - 4;
+        bytes.splice(position..position + 1, [0x80, 0x80, 0x00]);
+        let refs = TabStateRefs::from_buffer(&bytes[..]).unwrap();
+
+        let (_, anomalies) = TabStateStreamParser::new(&bytes[..])
+            .parse_with_anomalies()
+            .unwrap();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].field, "chunks[0].position");
+        assert_eq!(anomalies[0].offset, position);
+        assert!(refs.get_anomalies().contains(&anomalies[0]));
+    }
+
+    /// A huge text length should fail at the end of the stream, not try to allocate the whole thing.
+    #[test]
+    fn huge_text_len() {
+        let mut bytes = b"NP\0\0\0\0\0\0\0\0".to_vec();
+        // u64::MAX as a varint
+        bytes.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]);
+        bytes.extend_from_slice(&[0x41, 0x00]);
+
+        let error = TabState::from_reader(&bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+
+        let error = TabState::from_seekable_reader(Cursor::new(&bytes)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
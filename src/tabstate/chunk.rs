@@ -0,0 +1,50 @@
+use crate::crc::crc32;
+use crate::tabstate::write_varint;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Owned version of an edit chunk.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EditChunk {
+    pub position: usize,
+    pub deletion_count: usize,
+    /// Size in chars of the added text.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub addition_count: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub added_text: String,
+    /// CRC32 of the chunk, not including the CRC.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub crc: Option<u32>,
+}
+
+impl EditChunk {
+    pub(crate) fn write(&self, buffer: &mut Vec<u8>) {
+        let start = buffer.len();
+        let added_text = self.added_text.encode_utf16().collect::<Vec<u16>>();
+
+        write_varint(buffer, self.position);
+        write_varint(buffer, self.deletion_count);
+        write_varint(buffer, self.addition_count.unwrap_or(added_text.len()));
+        for c in added_text {
+            buffer.extend_from_slice(&c.to_le_bytes());
+        }
+
+        let crc = self.crc.unwrap_or_else(|| crc32(&buffer[start..]));
+        buffer.extend_from_slice(&crc.to_be_bytes());
+    }
+    /// Applies this edit to the provided UTF-16 text. Positions past the end of the text are clamped
+    /// to the end, so a bad chunk can't panic.
+    pub fn apply(&self, text: &mut Vec<u16>) {
+        let position = self.position.min(text.len());
+        let deletion_end = position.saturating_add(self.deletion_count).min(text.len());
+        text.splice(position..deletion_end, self.added_text.encode_utf16());
+    }
+}
@@ -0,0 +1,73 @@
+use crate::enums::{CarriageType, Encoding};
+use crate::tabstate::{write_varint, write_wide_buffer};
+use crate::text::{convert_line_endings, hash_text, CodePage, LineEnding};
+use crate::varint::VarInt;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Owned version of the metadata that saved files have.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    pub path: String,
+    /// Size in chars of the file path.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub path_len: Option<usize>,
+    /// The full size in chars of the text buffer on disk. If this is `None`, it's calculated from the
+    /// text buffer, with the line breaks converted to the ones in `carriage_type`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub full_buffer_size: Option<usize>,
+    pub encoding: Encoding,
+    pub carriage_type: CarriageType,
+    pub filetime: u64,
+    #[cfg_attr(feature = "serde", serde(default, with = "crate::tabstate::hex"))]
+    pub content_hash: [u8; 0x20],
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unk: u8,
+    #[cfg_attr(feature = "serde", serde(default = "default_unk2"))]
+    pub unk2: u8,
+}
+
+impl Metadata {
+    /// The line breaks the file on disk uses.
+    pub fn line_ending(&self) -> LineEnding {
+        self.carriage_type.into()
+    }
+    /// Calculates the size of the text on disk. Notepad only keeps `\r` for line breaks in the text
+    /// buffer, so CRLF files have one extra char per line break.
+    pub fn calculate_full_buffer_size(&self, text: &str) -> usize {
+        convert_line_endings(text, self.line_ending())
+            .encode_utf16()
+            .count()
+    }
+    /// Calculates what `content_hash` should be if `text` is what's on disk, with this file's line
+    /// breaks and encoding. `code_page` is only used for ANSI files.
+    pub fn calculate_content_hash(&self, text: &str, code_page: CodePage) -> [u8; 0x20] {
+        hash_text(text, self.encoding, self.line_ending(), code_page)
+    }
+    pub(crate) fn write(&self, buffer: &mut Vec<u8>, text: &str) {
+        write_wide_buffer(buffer, &self.path, self.path_len);
+        write_varint(
+            buffer,
+            self.full_buffer_size
+                .unwrap_or_else(|| self.calculate_full_buffer_size(text)),
+        );
+        buffer.push(self.encoding.as_value());
+        buffer.push(self.carriage_type.as_value());
+        buffer.extend_from_slice(VarInt::new(self.filetime as u128).get_buffer());
+        buffer.extend_from_slice(&self.content_hash);
+        buffer.push(self.unk);
+        buffer.push(self.unk2);
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_unk2() -> u8 {
+    crate::consts::METADATA_UNK2
+}
@@ -0,0 +1,297 @@
+#![doc = "An owned version of a TabState file. Unlike `TabStateRefs`, this can be built by hand, or deserialized, and written back out as a TabState buffer."]
+
+use crate::consts::{CRC_START, MAGIC};
+use crate::crc::crc32;
+use crate::header::State;
+use crate::options::TabStateOptions;
+use crate::refs::tabstate::TabStateRefs;
+use crate::tabstate::chunk::EditChunk;
+use crate::tabstate::metadata::Metadata;
+use crate::enums::Encoding;
+use crate::text::{convert_line_endings, encode_text, CodePage, EncodedText, LineEnding};
+use crate::varint::VarInt;
+use crate::version::FormatVersion;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub mod chunk;
+pub mod metadata;
+
+/// An owned TabState file. Length fields and the CRC are optional. If they are `None`, they are
+/// calculated when the file is written with `to_bytes`. If they are provided, they are written as is,
+/// even if they are wrong, so you can build broken files on purpose.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TabState {
+    /// The third byte of the magic. This is the format version, see `FormatVersion`. It has always
+    /// been 0 in the samples I have seen.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub header_unk: u8,
+    /// Only saved files have metadata. If this is `None` the file is written as `State::Unsaved`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: Option<Metadata>,
+    pub cursor_start: usize,
+    pub cursor_end: usize,
+    pub options: TabStateOptions,
+    pub text: String,
+    /// Size in chars of the text buffer.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub text_len: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub the_number_zero: u8,
+    /// CRC32 of everything after the "NP" magic up to the CRC.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub crc: Option<u32>,
+    /// Edits that come after the footer, in the order Notepad wrote them.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub chunks: Vec<EditChunk>,
+}
+
+impl From<&TabStateRefs<'_>> for TabState {
+    fn from(refs: &TabStateRefs<'_>) -> Self {
+        refs.to_tab_state()
+    }
+}
+
+impl TabState {
+    /// Parses the TabState file from a given buffer and copies it into a new `TabState`.
+    pub fn from_buffer(buffer: &[u8]) -> crate::error::Result<Self> {
+        Ok(TabStateRefs::from_buffer(buffer)?.to_tab_state())
+    }
+    /// The state that will be written to the header.
+    pub fn get_state(&self) -> State {
+        match self.metadata {
+            Some(_) => State::Saved,
+            None => State::Unsaved,
+        }
+    }
+    /// The format version from `header_unk`, if it's one we know.
+    pub fn version(&self) -> Option<FormatVersion> {
+        FormatVersion::from_header_byte(self.header_unk)
+    }
+    /// Writes this `TabState` out as a TabState buffer. Any length or CRC that is `None` is calculated.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+
+        buffer.extend_from_slice(&MAGIC[..2]);
+        buffer.push(self.header_unk);
+        buffer.push(self.get_state() as u8);
+
+        if let Some(metadata) = &self.metadata {
+            metadata.write(&mut buffer, &self.text);
+        }
+
+        write_varint(&mut buffer, self.cursor_start);
+        write_varint(&mut buffer, self.cursor_end);
+        buffer.extend_from_slice(&self.options.as_bytes());
+        write_wide_buffer(&mut buffer, &self.text, self.text_len);
+
+        buffer.push(self.the_number_zero);
+        let crc = self.crc.unwrap_or_else(|| crc32(&buffer[CRC_START..]));
+        buffer.extend_from_slice(&crc.to_be_bytes());
+
+        for chunk in &self.chunks {
+            chunk.write(&mut buffer);
+        }
+
+        buffer
+    }
+    /// Applies each edit chunk to the text buffer, in order, and returns the text as Notepad would
+    /// show it.
+    pub fn reconstruct_text(&self) -> String {
+        let mut text = self.text.encode_utf16().collect::<Vec<u16>>();
+        for chunk in &self.chunks {
+            chunk.apply(&mut text);
+        }
+        String::from_utf16_lossy(&text[..])
+    }
+    /// The line breaks the tab is saved with. Unsaved tabs get `LineEnding::DEFAULT`, same as Notepad.
+    pub fn line_ending(&self) -> LineEnding {
+        self.metadata
+            .as_ref()
+            .map_or(LineEnding::DEFAULT, |m| m.line_ending())
+    }
+    /// The reconstructed text, with every line break written as `ending`.
+    pub fn text_with_line_endings(&self, ending: LineEnding) -> String {
+        convert_line_endings(&self.reconstruct_text(), ending)
+    }
+    /// The reconstructed text, with the line breaks the tab is saved with. This is what Notepad would
+    /// write to disk, before encoding.
+    pub fn saved_text(&self) -> String {
+        self.text_with_line_endings(self.line_ending())
+    }
+    /// Encodes `saved_text` in the tab's encoding, the way Notepad would write the file. Unsaved tabs
+    /// get UTF-8, Notepad's default. `code_page` is only used for ANSI files, and any chars it
+    /// doesn't have are reported in `EncodedText::unmappable`.
+    pub fn to_saved_bytes(&self, code_page: CodePage) -> EncodedText {
+        let encoding = self.metadata.as_ref().map_or(Encoding::UTF8, |m| m.encoding);
+        encode_text(&self.saved_text(), encoding, code_page)
+    }
+}
+
+#[inline(always)]
+pub(crate) fn write_varint(buffer: &mut Vec<u8>, num: usize) {
+    buffer.extend_from_slice(VarInt::new(num as u128).get_buffer());
+}
+
+/// Writes a varint length followed by the UTF-16LE text. If `len` is `None`, the length is the amount
+/// of UTF-16 chars in `text`.
+pub(crate) fn write_wide_buffer(buffer: &mut Vec<u8>, text: &str, len: Option<usize>) {
+    let chars = text.encode_utf16().collect::<Vec<u16>>();
+    write_varint(buffer, len.unwrap_or(chars.len()));
+    for c in chars {
+        buffer.extend_from_slice(&c.to_le_bytes());
+    }
+}
+
+/// Serializes byte arrays as hex strings, so hashes are readable in JSON.
+#[cfg(feature = "serde")]
+pub(crate) mod hex {
+    use alloc::format;
+    use alloc::string::String;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let hex = bytes.iter().map(|b| format!("{b:02X}")).collect::<String>();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() != N * 2 || !hex.is_ascii() {
+            return Err(D::Error::custom(format!(
+                "Expected {} hex characters. Got: {hex:?}",
+                N * 2
+            )));
+        }
+
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(D::Error::custom)?;
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::tabstate::chunk::EditChunk;
+    use crate::tabstate::TabState;
+    use crate::test_util::saved_tab;
+
+    /// Writing a `TabState` and parsing it again should give back the same values, with the computed
+    /// lengths and CRC filled in.
+    #[test]
+    fn round_trip_saved() {
+        let tab = saved_tab();
+        let bytes = tab.to_bytes();
+        let parsed = TabState::from_buffer(&bytes[..]).unwrap();
+
+        let metadata = parsed.metadata.as_ref().unwrap();
+        assert_eq!(metadata.path_len, Some(30));
+        // The text has one line break, which is two chars on disk for CRLF.
+        assert_eq!(metadata.full_buffer_size, Some(12));
+        assert_eq!(parsed.text_len, Some(11));
+        assert!(parsed.crc.is_some());
+
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn round_trip_unsaved() {
+        let tab = TabState {
+            metadata: None,
+            ..saved_tab()
+        };
+        let bytes = tab.to_bytes();
+        assert_eq!(&bytes[..4], b"NP\0\0");
+
+        let parsed = TabState::from_buffer(&bytes[..]).unwrap();
+        assert_eq!(parsed.text, tab.text);
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn edit_chunks() {
+        let tab = TabState {
+            metadata: None,
+            text: "Hello".to_string(),
+            chunks: vec![
+                EditChunk {
+                    position: 5,
+                    deletion_count: 0,
+                    addition_count: None,
+                    added_text: " World".to_string(),
+                    crc: None,
+                },
+                EditChunk {
+                    position: 0,
+                    deletion_count: 5,
+                    addition_count: None,
+                    added_text: "Goodbye".to_string(),
+                    crc: None,
+                },
+            ],
+            ..saved_tab()
+        };
+        let bytes = tab.to_bytes();
+
+        let refs = TabStateRefs::from_buffer(&bytes[..]).unwrap();
+        assert_eq!(refs.get_chunks().len(), 2);
+        assert!(refs.is_crc_valid());
+
+        // A chunk that is only partly written should fail the CRC check.
+        let partial = &bytes[..bytes.len() - 2];
+        let partial_refs = TabStateRefs::from_buffer(partial).unwrap();
+        assert_eq!(partial_refs.get_chunks().len(), 1);
+        assert!(!partial_refs.get_trailing_bytes().is_empty());
+        assert!(!partial_refs.is_crc_valid());
+
+        let parsed = refs.to_tab_state();
+        assert_eq!(parsed.reconstruct_text(), "Goodbye World");
+        assert_eq!(parsed.to_bytes(), bytes);
+    }
+
+    #[test]
+    fn rejects_unknown_carriage_types() {
+        let mut bytes = saved_tab().to_bytes();
+        let spans = crate::spans::skim_field_spans(&bytes);
+        let carriage_type = spans
+            .iter()
+            .find(|s| s.field == "metadata.carriage_type")
+            .unwrap();
+        bytes[carriage_type.offset] = 2;
+
+        assert!(TabStateRefs::from_buffer(&bytes[..]).is_err());
+        assert!(TabState::from_reader(&bytes[..]).is_err());
+    }
+
+    /// Lengths that are provided are written as is.
+    #[test]
+    fn explicit_crc_is_kept() {
+        let tab = TabState {
+            crc: Some(0xDEADBEEF),
+            ..saved_tab()
+        };
+        let bytes = tab.to_bytes();
+        assert_eq!(&bytes[bytes.len() - 4..], &[0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+}
@@ -0,0 +1,58 @@
+#![doc = "Fixtures for the tests, so every module starts from the same tabs. Change what a test needs with struct update syntax, like `TabState { text: \"Hi\".to_string(), ..saved_tab() }`. Only meant for tests, which is why it's hidden, and only public for the C API tests."]
+
+use crate::enums::{CarriageType, Encoding};
+use crate::options::TabStateOptions;
+use crate::tabstate::chunk::EditChunk;
+use crate::tabstate::metadata::Metadata;
+use crate::tabstate::TabState;
+use alloc::string::ToString;
+use alloc::vec;
+
+/// A saved tab with one line break in the text and no edit chunks. The lengths and CRC are left for
+/// `to_bytes` to fill in.
+pub fn saved_tab() -> TabState {
+    TabState {
+        header_unk: 0,
+        metadata: Some(Metadata {
+            path: r"C:\Users\Nord\Desktop\test.txt".to_string(),
+            path_len: None,
+            full_buffer_size: None,
+            encoding: Encoding::UTF8,
+            carriage_type: CarriageType::CRLF,
+            filetime: 133_450_000_000_000_000,
+            content_hash: [0xAB; 0x20],
+            unk: 0,
+            unk2: 1,
+        }),
+        cursor_start: 3,
+        cursor_end: 5,
+        options: TabStateOptions::new(1, 0, 0, 1),
+        text: "Hello\rWorld".to_string(),
+        text_len: None,
+        the_number_zero: 0,
+        crc: None,
+        chunks: vec![],
+    }
+}
+
+/// An unsaved tab with "Hello" in it, and one edit chunk that adds a "!" at the end.
+pub fn unsaved_tab() -> TabState {
+    TabState {
+        header_unk: 0,
+        metadata: None,
+        cursor_start: 0,
+        cursor_end: 0,
+        options: TabStateOptions::new(1, 0, 0, 1),
+        text: "Hello".to_string(),
+        text_len: None,
+        the_number_zero: 0,
+        crc: None,
+        chunks: vec![EditChunk {
+            position: 5,
+            deletion_count: 0,
+            addition_count: None,
+            added_text: "!".to_string(),
+            crc: None,
+        }],
+    }
+}
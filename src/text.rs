@@ -0,0 +1,345 @@
+#![doc = "Turns the text buffer back into the text of the file on disk. Notepad only keeps `\\r` for line breaks in the buffer, and remembers what the file used in `CarriageType`, so anything that compares text with a file has to put the line breaks back first."]
+
+use crate::enums::{CarriageType, Encoding};
+use alloc::string::String;
+use alloc::vec::Vec;
+use encoding_rs::EncoderResult;
+use sha2::{Digest, Sha256};
+
+/// The line break to write text out with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineEnding {
+    LF,
+    CRLF,
+    CR,
+}
+
+impl LineEnding {
+    /// What Notepad uses for new files, so it's what unsaved tabs get.
+    pub const DEFAULT: Self = LineEnding::CRLF;
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::LF => "\n",
+            LineEnding::CRLF => "\r\n",
+            LineEnding::CR => "\r",
+        }
+    }
+}
+
+impl From<CarriageType> for LineEnding {
+    fn from(carriage_type: CarriageType) -> Self {
+        match carriage_type {
+            CarriageType::Unix => LineEnding::LF,
+            CarriageType::CRLF => LineEnding::CRLF,
+        }
+    }
+}
+
+/// Writes every line break in `text` as `ending`. Notepad's buffer only has `\r`, but `\r\n` and `\n`
+/// are counted as one line break too, so text that came from somewhere else, like a JSON file, comes
+/// out the same way.
+pub fn convert_line_endings(text: &str, ending: LineEnding) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                chars.next_if_eq(&'\n');
+                output.push_str(ending.as_str());
+            }
+            '\n' => output.push_str(ending.as_str()),
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
+/// The code page an `Encoding::ANSI` file was saved in. Notepad uses the system's ANSI code page,
+/// which isn't saved anywhere in the TabState, so it has to be picked. `CodePage::default()` is
+/// Windows-1252, the code page for English and most Western European systems.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CodePage(&'static encoding_rs::Encoding);
+
+impl Default for CodePage {
+    fn default() -> Self {
+        CodePage(encoding_rs::WINDOWS_1252)
+    }
+}
+
+impl CodePage {
+    /// Gets a code page from its Windows number, like `1252` or `932`. Only the code pages Windows
+    /// can use as its ANSI code page are known.
+    pub fn from_number(number: u16) -> Option<Self> {
+        let encoding = match number {
+            874 => encoding_rs::WINDOWS_874,
+            932 => encoding_rs::SHIFT_JIS,
+            936 => encoding_rs::GBK,
+            949 => encoding_rs::EUC_KR,
+            950 => encoding_rs::BIG5,
+            1250 => encoding_rs::WINDOWS_1250,
+            1251 => encoding_rs::WINDOWS_1251,
+            1252 => encoding_rs::WINDOWS_1252,
+            1253 => encoding_rs::WINDOWS_1253,
+            1254 => encoding_rs::WINDOWS_1254,
+            1255 => encoding_rs::WINDOWS_1255,
+            1256 => encoding_rs::WINDOWS_1256,
+            1257 => encoding_rs::WINDOWS_1257,
+            1258 => encoding_rs::WINDOWS_1258,
+            65001 => encoding_rs::UTF_8,
+            _ => return None,
+        };
+
+        Some(CodePage(encoding))
+    }
+    /// Gets a code page from a number (`1252`, `cp1252`) or a WHATWG label (`windows-1252`,
+    /// `shift_jis`). UTF-16 is not a code page, so it's rejected.
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim();
+        let number = label
+            .strip_prefix("cp")
+            .or_else(|| label.strip_prefix("CP"))
+            .unwrap_or(label);
+        if let Ok(number) = number.parse() {
+            return Self::from_number(number);
+        }
+
+        let encoding = encoding_rs::Encoding::for_label(label.as_bytes())?;
+        // UTF-16 and the replacement encoding can't be encoded to, they encode to UTF-8 instead.
+        if encoding.output_encoding() != encoding {
+            return None;
+        }
+
+        Some(CodePage(encoding))
+    }
+    /// The WHATWG name of the code page, like `windows-1252`.
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+    /// Encodes text in this code page. Chars the code page doesn't have are written as `?`, same as
+    /// Windows does, and reported in `EncodedText::unmappable`.
+    pub fn encode(&self, text: &str) -> EncodedText {
+        let mut encoder = self.0.new_encoder();
+        let mut bytes = Vec::with_capacity(text.len());
+        let mut unmappable = Vec::new();
+        let mut remaining = text;
+        let mut offset = 0;
+        loop {
+            let needed = encoder
+                .max_buffer_length_from_utf8_without_replacement(remaining.len())
+                .unwrap_or(remaining.len() * 4);
+            bytes.reserve(needed);
+
+            let (result, read) =
+                encoder.encode_from_utf8_to_vec_without_replacement(remaining, &mut bytes, true);
+            offset += remaining[..read].encode_utf16().count();
+            remaining = &remaining[read..];
+
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => {}
+                EncoderResult::Unmappable(c) => {
+                    unmappable.push(Unmappable {
+                        offset: offset - c.len_utf16(),
+                        c,
+                    });
+                    bytes.push(b'?');
+                }
+            }
+        }
+
+        EncodedText { bytes, unmappable }
+    }
+    /// Decodes bytes in this code page. Bytes the code page doesn't map are decoded as U+FFFD.
+    pub fn decode(&self, bytes: &[u8]) -> DecodedText {
+        let (text, had_errors) = self.0.decode_without_bom_handling(bytes);
+        DecodedText {
+            text: text.into_owned(),
+            had_errors,
+        }
+    }
+}
+
+/// A char that the code page doesn't have.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Unmappable {
+    /// Offset in UTF-16 chars into the text, same as the cursor.
+    pub offset: usize,
+    pub c: char,
+}
+
+/// Text encoded for disk, along with any chars that couldn't be encoded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodedText {
+    pub bytes: Vec<u8>,
+    /// Only ANSI files can have these. Each one was written as `?`.
+    pub unmappable: Vec<Unmappable>,
+}
+
+/// Text decoded from disk.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedText {
+    pub text: String,
+    /// Whether any bytes were invalid for the encoding and decoded as U+FFFD.
+    pub had_errors: bool,
+}
+
+/// Encodes text the way Notepad saves it with `encoding`, BOM included. `code_page` is only used for
+/// `Encoding::ANSI`. Line breaks are written as is, so convert them first.
+pub fn encode_text(text: &str, encoding: Encoding, code_page: CodePage) -> EncodedText {
+    let bytes = match encoding {
+        Encoding::ANSI => return code_page.encode(text),
+        Encoding::UTF8 => text.as_bytes().to_vec(),
+        Encoding::UTF8BOM => [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat(),
+        Encoding::UTF16LE => [0xFEFF]
+            .into_iter()
+            .chain(text.encode_utf16())
+            .flat_map(u16::to_le_bytes)
+            .collect(),
+        Encoding::UTF16BE => [0xFEFF]
+            .into_iter()
+            .chain(text.encode_utf16())
+            .flat_map(u16::to_be_bytes)
+            .collect(),
+    };
+
+    EncodedText {
+        bytes,
+        unmappable: Vec::new(),
+    }
+}
+
+/// Decodes the bytes of a file saved with `encoding`, dropping the BOM if there is one. `code_page` is
+/// only used for `Encoding::ANSI`. Line breaks are kept as is.
+pub fn decode_text(bytes: &[u8], encoding: Encoding, code_page: CodePage) -> DecodedText {
+    let encoding = match encoding {
+        Encoding::ANSI => return code_page.decode(bytes),
+        Encoding::UTF8 | Encoding::UTF8BOM => encoding_rs::UTF_8,
+        Encoding::UTF16LE => encoding_rs::UTF_16LE,
+        Encoding::UTF16BE => encoding_rs::UTF_16BE,
+    };
+    let (text, had_errors) = encoding.decode_with_bom_removal(bytes);
+
+    DecodedText {
+        text: text.into_owned(),
+        had_errors,
+    }
+}
+
+/// The SHA-256 of a file's bytes, in the same form as `Metadata::content_hash`.
+pub fn hash_bytes(bytes: &[u8]) -> [u8; 0x20] {
+    Sha256::digest(bytes).into()
+}
+
+/// The SHA-256 of the text as it would be saved, in the same form as `Metadata::content_hash`.
+/// Unmappable chars in ANSI files are hashed as `?`, same as they would be saved.
+pub fn hash_text(
+    text: &str,
+    encoding: Encoding,
+    ending: LineEnding,
+    code_page: CodePage,
+) -> [u8; 0x20] {
+    let encoded = encode_text(&convert_line_endings(text, ending), encoding, code_page);
+    hash_bytes(&encoded.bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enums::{CarriageType, Encoding};
+    use crate::text::{
+        convert_line_endings, decode_text, encode_text, hash_text, CodePage, LineEnding, Unmappable,
+    };
+
+    #[test]
+    fn converts_line_endings() {
+        let text = "a\rb\r\rc";
+        assert_eq!(convert_line_endings(text, LineEnding::LF), "a\nb\n\nc");
+        assert_eq!(
+            convert_line_endings(text, LineEnding::CRLF),
+            "a\r\nb\r\n\r\nc"
+        );
+        assert_eq!(convert_line_endings(text, LineEnding::CR), text);
+        // Line breaks that aren't Notepad's own count once each.
+        assert_eq!(convert_line_endings("a\r\nb\nc", LineEnding::CR), "a\rb\rc");
+        assert_eq!(LineEnding::from(CarriageType::Unix), LineEnding::LF);
+    }
+
+    #[test]
+    fn encodes_with_bom() {
+        let encode = |encoding| encode_text("hi", encoding, CodePage::default()).bytes;
+        assert_eq!(encode(Encoding::UTF8), b"hi");
+        assert_eq!(encode(Encoding::UTF8BOM), b"\xEF\xBB\xBFhi");
+        assert_eq!(encode(Encoding::UTF16LE), b"\xFF\xFEh\x00i\x00");
+        assert_eq!(encode(Encoding::UTF16BE), b"\xFE\xFF\x00h\x00i");
+        assert_eq!(encode(Encoding::ANSI), b"hi");
+
+        for encoding in [Encoding::UTF8BOM, Encoding::UTF16LE, Encoding::UTF16BE] {
+            let bytes = encode_text("hé", encoding, CodePage::default()).bytes;
+            assert_eq!(
+                decode_text(&bytes, encoding, CodePage::default()).text,
+                "hé"
+            );
+        }
+    }
+
+    #[test]
+    fn ansi_code_pages() {
+        let cp1252 = CodePage::default();
+        let encoded = cp1252.encode("café 日€");
+        assert_eq!(encoded.bytes, b"caf\xE9 ?\x80");
+        assert_eq!(
+            encoded.unmappable,
+            [Unmappable {
+                offset: 5, c: '日'
+            }]
+        );
+        assert_eq!(cp1252.decode(&encoded.bytes).text, "café ?€");
+
+        // Offsets are in UTF-16 chars, so the emoji counts as two.
+        let encoded = cp1252.encode("😀a😀");
+        assert_eq!(encoded.bytes, b"?a?");
+        let offsets = encoded
+            .unmappable
+            .iter()
+            .map(|u| u.offset)
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 3]);
+
+        let cp932 = CodePage::from_label("932").unwrap();
+        assert_eq!(cp932.name(), "Shift_JIS");
+        let encoded = encode_text("日本", Encoding::ANSI, cp932);
+        assert_eq!(encoded.bytes, b"\x93\xFA\x96\x7B");
+        assert!(encoded.unmappable.is_empty());
+        assert_eq!(
+            decode_text(&encoded.bytes, Encoding::ANSI, cp932).text,
+            "日本"
+        );
+
+        assert_eq!(
+            CodePage::from_label("cp1251").unwrap().name(),
+            "windows-1251"
+        );
+        assert_eq!(
+            CodePage::from_label("windows-1253").unwrap().name(),
+            "windows-1253"
+        );
+        assert_eq!(CodePage::from_label("utf-16le"), None);
+        assert_eq!(CodePage::from_label("1234"), None);
+    }
+
+    #[test]
+    fn hashes_saved_text() {
+        // sha256("a\r\nb")
+        let expected = "18745f36a05e29072709042d6062ce54f1b08ff36c27ba80c39f81fb010c8ce2";
+        let hash = hash_text(
+            "a\rb",
+            Encoding::UTF8,
+            LineEnding::CRLF,
+            CodePage::default(),
+        );
+        let hex = hash.iter().map(|b| format!("{b:02x}")).collect::<String>();
+        assert_eq!(hex, expected);
+    }
+}
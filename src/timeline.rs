@@ -0,0 +1,223 @@
+#![doc = "Turns TabState files into timestamped events for forensic timelines. Events can be written as a bodyfile for mactime, or as JSON lines that Timesketch can import."]
+
+use crate::dir::TabStateFile;
+use crate::filetime::filetime_to_unix_micros;
+#[cfg(feature = "serde")]
+use crate::filetime::format_iso8601;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TimelineEventKind {
+    /// `TabStateMetadata::filetime`. This is the last write time of the file the tab has open.
+    FileTime,
+    /// Last modified time of the TabState file itself.
+    Modified,
+    /// Creation time of the TabState file itself.
+    Created,
+    /// An edit chunk. Chunks don't have their own timestamp, so these use the modified time of the
+    /// TabState file, which is the latest the edit could have happened. Use `edit_sequence` for the
+    /// order.
+    Edit,
+}
+
+impl TimelineEventKind {
+    /// A short description of what the timestamp means.
+    pub fn description(&self) -> &'static str {
+        match self {
+            TimelineEventKind::FileTime => "Tab File Last Written",
+            TimelineEventKind::Modified => "TabState Modified",
+            TimelineEventKind::Created => "TabState Created",
+            TimelineEventKind::Edit => "TabState Unsaved Edit",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineEvent {
+    /// Microseconds since the unix epoch.
+    pub timestamp: i64,
+    pub kind: TimelineEventKind,
+    pub tabstate_path: PathBuf,
+    /// The path of the file the tab has open. Unsaved tabs don't have one.
+    pub file_path: Option<String>,
+    /// The index of the edit chunk, for `TimelineEventKind::Edit` events.
+    pub edit_sequence: Option<usize>,
+    pub message: String,
+}
+
+pub(crate) fn system_time_to_unix_micros(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_micros() as i64,
+        Err(e) => -(e.duration().as_micros() as i64),
+    }
+}
+
+/// Gets every event for a single TabState file. Files that failed to parse still get events for the
+/// file system timestamps.
+pub fn get_events(file: &TabStateFile) -> Vec<TimelineEvent> {
+    let tab = file.tab.as_ref().ok();
+    let file_path = tab
+        .and_then(|t| t.metadata.as_ref())
+        .map(|m| m.path.clone());
+    let name = file_path.as_deref().unwrap_or("Unsaved tab");
+
+    let event = |timestamp, kind: TimelineEventKind, edit_sequence, message| TimelineEvent {
+        timestamp,
+        kind,
+        tabstate_path: file.path.clone(),
+        file_path: file_path.clone(),
+        edit_sequence,
+        message,
+    };
+
+    let mut events = vec![];
+    // A zero filetime just means it was never set, so there is no event for it.
+    if let Some(metadata) = tab
+        .and_then(|t| t.metadata.as_ref())
+        .filter(|m| m.filetime != 0)
+    {
+        events.push(event(
+            filetime_to_unix_micros(metadata.filetime),
+            TimelineEventKind::FileTime,
+            None,
+            format!("{}: {name}", TimelineEventKind::FileTime.description()),
+        ));
+    }
+
+    if let Ok(created) = file.fs_metadata.created() {
+        events.push(event(
+            system_time_to_unix_micros(created),
+            TimelineEventKind::Created,
+            None,
+            format!("{}: {name}", TimelineEventKind::Created.description()),
+        ));
+    }
+
+    let modified = match file.fs_metadata.modified() {
+        Ok(modified) => system_time_to_unix_micros(modified),
+        Err(_) => return events,
+    };
+    events.push(event(
+        modified,
+        TimelineEventKind::Modified,
+        None,
+        format!("{}: {name}", TimelineEventKind::Modified.description()),
+    ));
+
+    for (i, chunk) in tab.iter().flat_map(|t| t.chunks.iter()).enumerate() {
+        events.push(event(
+            modified,
+            TimelineEventKind::Edit,
+            Some(i),
+            format!(
+                "{} #{i}: {name} at char {} deleted {} added {:?}",
+                TimelineEventKind::Edit.description(),
+                chunk.position,
+                chunk.deletion_count,
+                chunk.added_text
+            ),
+        ));
+    }
+
+    events
+}
+
+/// Gets the events for every file, sorted by timestamp. Events with the same timestamp keep the order
+/// of the files, and edits stay in sequence order.
+pub fn get_timeline(files: &[TabStateFile]) -> Vec<TimelineEvent> {
+    let mut events = files.iter().flat_map(get_events).collect::<Vec<_>>();
+    events.sort_by_key(|e| e.timestamp);
+    events
+}
+
+/// Writes the events in the mactime bodyfile format. Each event gets its own line, with the timestamp
+/// in the column that matches the event. The name is the TabState path followed by the message.
+pub fn write_bodyfile<W: Write>(writer: &mut W, events: &[TimelineEvent]) -> std::io::Result<()> {
+    for e in events {
+        let time = e.timestamp.div_euclid(1_000_000);
+        let (mtime, crtime) = match e.kind {
+            TimelineEventKind::Created => (0, time),
+            _ => (time, 0),
+        };
+        // MD5|name|inode|mode_as_string|UID|GID|size|atime|mtime|ctime|crtime
+        writeln!(
+            writer,
+            "0|{}|0|0|0|0|0|0|{mtime}|0|{crtime}",
+            sanitize_bodyfile_name(&format!("{} ({})", e.tabstate_path.display(), e.message))
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The bodyfile format is pipe delimited and line based, and mactime has no way to escape either, so
+/// pipes become a fullwidth `｜` and line breaks become spaces.
+fn sanitize_bodyfile_name(name: &str) -> String {
+    name.replace('|', "\u{FF5C}").replace(['\r', '\n'], " ")
+}
+
+/// Writes the events as JSON lines in the format Timesketch imports.
+#[cfg(feature = "serde")]
+pub fn write_jsonl<W: Write>(writer: &mut W, events: &[TimelineEvent]) -> std::io::Result<()> {
+    #[derive(serde::Serialize)]
+    struct TimesketchEvent<'a> {
+        message: &'a str,
+        datetime: String,
+        timestamp: i64,
+        timestamp_desc: &'static str,
+        kind: TimelineEventKind,
+        tabstate_path: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_path: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        edit_sequence: Option<usize>,
+    }
+
+    for e in events {
+        let event = TimesketchEvent {
+            message: &e.message,
+            datetime: format_iso8601(e.timestamp),
+            timestamp: e.timestamp,
+            timestamp_desc: e.kind.description(),
+            kind: e.kind,
+            tabstate_path: e.tabstate_path.display().to_string(),
+            file_path: e.file_path.as_deref(),
+            edit_sequence: e.edit_sequence,
+        };
+        serde_json::to_writer(&mut *writer, &event)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timeline::{write_bodyfile, TimelineEvent, TimelineEventKind};
+    use std::path::PathBuf;
+
+    #[test]
+    fn bodyfile_names_keep_the_columns() {
+        let event = TimelineEvent {
+            timestamp: 1_704_067_200_000_000,
+            kind: TimelineEventKind::FileTime,
+            tabstate_path: PathBuf::from("tab.bin"),
+            file_path: Some(r"C:\a|b.txt".to_string()),
+            edit_sequence: None,
+            message: "C:\\a|b.txt\r\nlast written".to_string(),
+        };
+        let mut output = vec![];
+        write_bodyfile(&mut output, &[event]).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 1);
+        let columns = lines[0].split('|').collect::<Vec<_>>();
+        assert_eq!(columns.len(), 11);
+        assert_eq!(columns[1], "tab.bin (C:\\a\u{FF5C}b.txt  last written)");
+        assert_eq!(columns[8], "1704067200");
+    }
+}
@@ -1,6 +1,9 @@
-use crate::consts::{MAX_VAL, SIGN_BIT};
+use crate::consts::{MAX_VARINT_LEN, MAX_VAL, SIGN_BIT};
+use crate::error::{Error, ErrorKind};
 use crate::refs::varint::VarIntRef;
-use std::io::{Error, ErrorKind};
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
 
 /// An integer that doesn't have a size in bytes at compile time.
 pub struct VarInt {
@@ -22,9 +25,9 @@ impl VarInt {
         Self { buffer }
     }
     /// Copies the provided buffer to a new vector and returns a `VarInt`. Returns an error if the provided
-    /// buffer is invalid, which includes empty buffer, leading bytes not being signed, or last byte
-    /// being signed
-    pub fn from_buffer(buffer: &[u8]) -> std::io::Result<Self> {
+    /// buffer is invalid, which includes empty buffer, leading bytes not being signed, last byte
+    /// being signed, or a value that doesn't fit in a `u128`
+    pub fn from_buffer(buffer: &[u8]) -> crate::error::Result<Self> {
         Ok(Self {
             buffer: validate_buffer(buffer)?.to_vec(),
         })
@@ -33,14 +36,14 @@ impl VarInt {
     ///
     /// # Safety
     ///
-    /// Does not check if the provided buffer is valid.
+    /// Does not check if the provided buffer is valid. It must pass `validate_buffer`.
     #[inline(always)]
     pub(crate) unsafe fn from_buffer_unchecked(buffer: &[u8]) -> Self {
         Self {
             buffer: buffer.to_vec(),
         }
     }
-    pub fn get_ref(&self) -> VarIntRef {
+    pub fn get_ref(&self) -> VarIntRef<'_> {
         unsafe { VarIntRef::new_unchecked(&self.buffer[..]) }
     }
     #[inline(always)]
@@ -51,17 +54,28 @@ impl VarInt {
     pub fn size_of(&self) -> usize {
         self.buffer.len()
     }
+    /// Returns false if the varint uses more bytes than it needs to. `VarInt::new` always writes the
+    /// shortest form, so only `from_buffer` can make one of these.
+    pub fn is_canonical(&self) -> bool {
+        is_canonical(self.get_buffer())
+    }
+    /// Decodes the value as a `usize`. Values that don't fit are clamped to `usize::MAX`, so use
+    /// `try_decode` if that matters.
     #[inline(always)]
     pub fn decode(&self) -> usize {
-        self.decode_lossless() as usize
+        self.try_decode().unwrap_or(usize::MAX)
+    }
+    /// Decodes the value as a `usize`, or returns an `ErrorKind::Overflow` error if it doesn't fit.
+    pub fn try_decode(&self) -> crate::error::Result<usize> {
+        to_usize(self.decode_lossless())
     }
     #[inline(always)]
     pub fn decode_lossless(&self) -> u128 {
-        decode(self.get_buffer())
+        decode_validated(self.get_buffer())
     }
 }
 
-pub fn validate_buffer(buffer: &[u8]) -> std::io::Result<&[u8]> {
+pub fn validate_buffer(buffer: &[u8]) -> crate::error::Result<&[u8]> {
     let last = match buffer.last() {
         Some(b) => b,
         None => {
@@ -88,19 +102,89 @@ pub fn validate_buffer(buffer: &[u8]) -> std::io::Result<&[u8]> {
         }
     }
 
+    decode(buffer)?;
+
+    Ok(buffer)
+}
+
+/// Returns true if `buffer` is the shortest encoding of its value. Every byte after the first adds 7
+/// more bits, so the only way to pad a varint is with trailing `0x80`s that end in a `0x00`, like
+/// `85 80 00` for 5. Notepad never writes those, so seeing one means something else wrote the file.
+pub fn is_canonical(buffer: &[u8]) -> bool {
+    buffer.len() <= 1 || buffer.last() != Some(&0)
+}
+
+/// Same as `validate_buffer`, but also returns an `ErrorKind::InvalidData` error if the varint is not
+/// in its shortest form.
+pub fn validate_canonical_buffer(buffer: &[u8]) -> crate::error::Result<&[u8]> {
+    validate_buffer(buffer)?;
+    if !is_canonical(buffer) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Varint {buffer:02X?} is padded. It should be {} bytes",
+                VarInt::new(decode_validated(buffer)).size_of()
+            ),
+        ));
+    }
+
     Ok(buffer)
 }
 
-pub fn decode(buffer: &[u8]) -> u128 {
-    let mut size = 0;
+/// Decodes the varint in `buffer`. Returns an `ErrorKind::Overflow` error if the buffer is longer than
+/// `MAX_VARINT_LEN`, or if the value doesn't fit in a `u128`. Sign bits are not checked, use
+/// `validate_buffer` for that.
+pub fn decode(buffer: &[u8]) -> crate::error::Result<u128> {
+    decode_bounded(buffer, MAX_VARINT_LEN, u128::BITS)
+}
+
+/// Same as `decode`, but only allows `max_len` bytes, and values that fit in `max_bits` bits.
+pub fn decode_bounded(buffer: &[u8], max_len: usize, max_bits: u32) -> crate::error::Result<u128> {
+    if buffer.len() > max_len {
+        return Err(Error::new(
+            ErrorKind::Overflow,
+            format!(
+                "Varint is {} bytes long. The most allowed is {max_len}",
+                buffer.len()
+            ),
+        ));
+    }
+
+    let mut size = 0u128;
     // We strip the sign bit off and bit shift the value to the right by 7 * i (since each byte only holds
     // 7 bits of data and this is little endian, so the byte furthest to the left is the least significant byte.)
     for (i, val) in buffer.iter().enumerate() {
         let num = (*val & MAX_VAL) as u128;
-        size |= num << (7 * i);
+        let shift = 7 * i as u32;
+        // If any of the bits would be shifted off the top, the value doesn't fit.
+        if num != 0 && num.leading_zeros() < shift {
+            return Err(overflow(max_bits));
+        }
+        size |= num.checked_shl(shift).unwrap_or(0);
     }
 
-    size
+    if max_bits < u128::BITS && size >> max_bits != 0 {
+        return Err(overflow(max_bits));
+    }
+
+    Ok(size)
+}
+
+/// Decodes a buffer that has already been checked with `validate_buffer`, so it can't fail.
+#[inline(always)]
+pub(crate) fn decode_validated(buffer: &[u8]) -> u128 {
+    decode(buffer).unwrap_or(u128::MAX)
+}
+
+pub(crate) fn to_usize(value: u128) -> crate::error::Result<usize> {
+    usize::try_from(value).map_err(|_| overflow(usize::BITS))
+}
+
+fn overflow(max_bits: u32) -> Error {
+    Error::new(
+        ErrorKind::Overflow,
+        format!("Varint value does not fit in {max_bits} bits"),
+    )
 }
 
 #[inline(always)]
@@ -110,8 +194,12 @@ fn get_chunk(val: u128) -> u8 {
 
 #[cfg(test)]
 mod tests {
-    use crate::consts::{MAX_VAL, SIGN_BIT};
-    use crate::varint::VarInt;
+    use crate::consts::{MAX_VARINT_LEN, MAX_VAL, SIGN_BIT};
+    use crate::error::ErrorKind;
+    use crate::reader::BufferReader;
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::refs::varint::VarIntRef;
+    use crate::varint::{decode, decode_bounded, VarInt};
 
     const TEST_ONE: [u8; 3] = [0xBB, 0x93, 0x2];
 
@@ -175,4 +263,94 @@ mod tests {
 
         let _ = VarInt::from_buffer(&buffer[..]).unwrap();
     }
+
+    #[test]
+    fn canonical_form() {
+        let padded = [0x85, 0x80, 0x00];
+        let varint = VarInt::from_buffer(&padded).unwrap();
+        assert_eq!(varint.decode(), 5);
+        assert!(!varint.is_canonical());
+        assert!(!VarIntRef::new(&padded).unwrap().is_canonical());
+        assert_eq!(
+            VarIntRef::new_canonical(&padded).err().unwrap().kind(),
+            ErrorKind::InvalidData
+        );
+
+        assert!(VarInt::new(5).is_canonical());
+        assert!(VarInt::new(0).is_canonical());
+        assert!(VarInt::new(0x80).is_canonical());
+        assert!(VarIntRef::new_canonical(&[0x80, 0x01]).is_ok());
+        assert!(VarIntRef::new_canonical(&[0x00]).is_ok());
+    }
+
+    /// The largest `u128` takes all 19 bytes, with only two bits used in the last one.
+    #[test]
+    fn decode_u128_max() {
+        let varint = VarInt::new(u128::MAX);
+        assert_eq!(varint.size_of(), MAX_VARINT_LEN);
+        assert_eq!(varint.get_buffer()[MAX_VARINT_LEN - 1], 0x03);
+        assert_eq!(decode(varint.get_buffer()).unwrap(), u128::MAX);
+    }
+
+    #[test]
+    fn decode_overflow() {
+        // One bit more than a u128 can hold.
+        let mut buffer = [0xFF; MAX_VARINT_LEN];
+        buffer[MAX_VARINT_LEN - 1] = 0x04;
+        assert_eq!(decode(&buffer).unwrap_err().kind(), ErrorKind::Overflow);
+        assert_eq!(
+            VarInt::from_buffer(&buffer).err().unwrap().kind(),
+            ErrorKind::Overflow
+        );
+
+        // Too long, even though the value is 0.
+        let mut buffer = [0x80; MAX_VARINT_LEN + 1];
+        buffer[MAX_VARINT_LEN] = 0;
+        assert_eq!(decode(&buffer).unwrap_err().kind(), ErrorKind::Overflow);
+
+        assert_eq!(
+            decode_bounded(&[0x80, 0x02], 2, 8).unwrap_err().kind(),
+            ErrorKind::Overflow
+        );
+        assert_eq!(decode_bounded(&[0x80, 0x02], 2, 9).unwrap(), 0x100);
+    }
+
+    /// A long run of signed bytes should stop at the max length, not scan to the end of the buffer.
+    #[test]
+    fn from_reader_is_bounded() {
+        let buffer = [0xFF; 0x1000];
+        let mut br = BufferReader::new(&buffer);
+        let error = VarIntRef::from_reader(&mut br).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::Overflow);
+
+        let buffer = [0x80, 0x80, 0x01];
+        let mut br = BufferReader::new(&buffer);
+        let error = VarIntRef::from_reader_bounded(&mut br, 2, u64::BITS).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::Overflow);
+    }
+
+    /// Values that don't fit in a `usize` are rejected by `from_reader`, and never truncated.
+    #[test]
+    fn no_silent_truncation() {
+        let varint = VarInt::new(u64::MAX as u128 + 1);
+        assert_eq!(varint.try_decode().unwrap_err().kind(), ErrorKind::Overflow);
+        assert_eq!(varint.decode(), usize::MAX);
+
+        let mut br = BufferReader::new(varint.get_buffer());
+        let error = VarIntRef::from_reader(&mut br).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::Overflow);
+
+        let mut br = BufferReader::new(varint.get_buffer());
+        let varint = VarIntRef::from_reader_bounded(&mut br, MAX_VARINT_LEN, u128::BITS).unwrap();
+        assert_eq!(varint.decode_lossless(), u64::MAX as u128 + 1);
+    }
+
+    /// A hostile text length in a TabState file should be a typed error.
+    #[test]
+    fn tabstate_with_long_varint() {
+        let mut bytes = b"NP\0\0".to_vec();
+        bytes.extend_from_slice(&[0xFF; 0x40]);
+        let error = TabStateRefs::from_buffer(&bytes).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::Overflow);
+    }
 }
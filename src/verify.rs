@@ -0,0 +1,476 @@
+#![doc = "Checks whether a TabState file looks like Notepad wrote it. Each check that fails adds a piece of evidence, and the worst piece decides the verdict for the file."]
+
+use crate::anomaly::AnomalyKind;
+#[cfg(feature = "std")]
+use crate::dir::{get_tabstate_paths, WindowStateFile};
+use crate::refs::tabstate::TabStateRefs;
+use crate::text::{hash_bytes, CodePage};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+/// How long after the TabState file was written the saved file's FILETIME can be, before we call it
+/// out. File systems round timestamps, so they don't always line up exactly.
+#[cfg(feature = "std")]
+pub const FILETIME_TOLERANCE_MICROS: i64 = 2_000_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Check {
+    /// The footer and edit chunk CRCs, and any partly written chunk.
+    Crc,
+    /// Varints that use more bytes than they need to.
+    CanonicalVarInt,
+    /// The cursor has to be inside the text.
+    CursorBounds,
+    /// `full_buffer_size` against the size calculated from the text.
+    FullBufferSize,
+    /// The saved file's FILETIME against the TabState file's own modified time, and whether it's a
+    /// FILETIME at all.
+    FileTime,
+    /// `content_hash` against the saved file and the tab's text.
+    ContentHash,
+    /// Whether a WindowState file lists the tab.
+    WindowState,
+    /// The file could not be parsed at all.
+    Parse,
+}
+
+/// How much a piece of evidence says about the file. Ordered from least to most.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Severity {
+    /// Worth knowing, but Notepad does this on its own, too.
+    Note,
+    /// Unusual for Notepad, but it can happen.
+    Suspicious,
+    /// Notepad doesn't write this, so something else touched the file.
+    Tampered,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Evidence {
+    pub check: Check,
+    pub severity: Severity,
+    /// The field, named the same way as in `spans`, if the evidence is about one field.
+    pub field: Option<String>,
+    /// The offset of the field in the file.
+    pub offset: Option<usize>,
+    pub message: String,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Verdict {
+    /// Nothing, or only notes, were found.
+    Consistent,
+    Suspicious,
+    Tampered,
+    /// The file could not be parsed, so nothing could be checked.
+    Unreadable,
+}
+
+impl Verdict {
+    /// The verdict for a parsed file, from the most severe piece of evidence.
+    pub fn from_evidence(evidence: &[Evidence]) -> Self {
+        match evidence.iter().map(|e| e.severity).max() {
+            Some(Severity::Tampered) => Verdict::Tampered,
+            Some(Severity::Suspicious) => Verdict::Suspicious,
+            Some(Severity::Note) | None => Verdict::Consistent,
+        }
+    }
+}
+
+/// Runs every check that only needs the file itself: CRCs, canonical varints, cursor bounds,
+/// `full_buffer_size` and whether the filetime is possible.
+pub fn verify_refs(refs: &TabStateRefs) -> Vec<Evidence> {
+    let mut evidence = vec![];
+
+    for anomaly in refs.get_anomalies() {
+        let (check, severity) = match anomaly.kind {
+            AnomalyKind::CrcMismatch => (Check::Crc, Severity::Tampered),
+            AnomalyKind::NonCanonicalVarInt => (Check::CanonicalVarInt, Severity::Tampered),
+            // Notepad leaves these behind when it's closed in the middle of writing a chunk.
+            AnomalyKind::TrailingBytes => (Check::Crc, Severity::Note),
+            // Unknown fields with new values are for bug reports, not evidence.
+            AnomalyKind::UnexpectedValue => continue,
+        };
+        evidence.push(Evidence {
+            check,
+            severity,
+            field: Some(anomaly.field.clone()),
+            offset: Some(anomaly.offset),
+            message: anomaly.message.clone(),
+        });
+    }
+
+    let buffer = refs.get_raw_buffer();
+    let text_len = refs.get_buffer_len().decode();
+    for (cursor, field) in [
+        (refs.get_cursor_start(), "cursor.start"),
+        (refs.get_cursor_end(), "cursor.end"),
+    ] {
+        if cursor.decode() > text_len {
+            evidence.push(Evidence {
+                check: Check::CursorBounds,
+                severity: Severity::Tampered,
+                field: Some(field.to_string()),
+                offset: Some(crate::reader::offset_of(buffer, cursor.get_buffer())),
+                message: format!(
+                    "Cursor is at char {}, but the text is only {text_len} chars",
+                    cursor.decode()
+                ),
+            });
+        }
+    }
+
+    if let Some(metadata) = refs.get_metadata() {
+        let stored = metadata.get_full_buffer_size().decode();
+        let calculated = metadata
+            .to_metadata()
+            .calculate_full_buffer_size(&String::from_utf16_lossy(&refs.get_buffer_chars()));
+        if stored != calculated {
+            evidence.push(Evidence {
+                check: Check::FullBufferSize,
+                severity: Severity::Note,
+                field: Some("metadata.full_buffer_size".to_string()),
+                offset: Some(crate::reader::offset_of(
+                    buffer,
+                    metadata.get_full_buffer_size().get_buffer(),
+                )),
+                message: format!(
+                    "Stored {stored}, but the text is {calculated} chars on disk. This is normal if \
+                    the tab has unsaved changes"
+                ),
+            });
+        }
+
+        // FILETIMEs are signed, and Windows won't convert negative ones, so Notepad can't have
+        // written one.
+        let filetime = metadata.get_filetime();
+        if filetime.decode_lossless() > i64::MAX as u128 {
+            evidence.push(Evidence {
+                check: Check::FileTime,
+                severity: Severity::Tampered,
+                field: Some("metadata.filetime".to_string()),
+                offset: Some(crate::reader::offset_of(buffer, filetime.get_buffer())),
+                message: format!("{:#X} is not a valid FILETIME", filetime.decode_lossless()),
+            });
+        }
+    }
+
+    evidence
+}
+
+/// Checks `content_hash` against `disk`, the contents of the file the tab is saved to. A hash that
+/// doesn't match is only a note, since the file can change after Notepad last loaded or saved it.
+/// But if the tab's text is exactly what's in the file, Notepad would have written that file's
+/// hash, so a different one is tampering. `code_page` is only used for ANSI files.
+pub fn verify_content_hash(
+    refs: &TabStateRefs,
+    disk: &[u8],
+    code_page: CodePage,
+) -> Option<Evidence> {
+    let metadata = refs.get_metadata()?;
+    let stored = *metadata.get_content_hash();
+    let disk_hash = hash_bytes(disk);
+    if stored == disk_hash {
+        return None;
+    }
+
+    let tab = refs.to_tab_state();
+    let calculated = metadata
+        .to_metadata()
+        .calculate_content_hash(&tab.reconstruct_text(), code_page);
+    let (severity, message) = match calculated == disk_hash {
+        true => (
+            Severity::Tampered,
+            "Doesn't match the saved file, even though the tab's text is the same as the file",
+        ),
+        false => (
+            Severity::Note,
+            "Doesn't match the saved file. It was probably changed after Notepad last loaded or \
+            saved it",
+        ),
+    };
+
+    Some(Evidence {
+        check: Check::ContentHash,
+        severity,
+        field: Some("metadata.content_hash".to_string()),
+        offset: Some(crate::reader::offset_of(
+            refs.get_raw_buffer(),
+            metadata.get_content_hash(),
+        )),
+        message: message.to_string(),
+    })
+}
+
+/// The result of verifying one file.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct VerifyReport {
+    pub path: PathBuf,
+    pub verdict: Verdict,
+    pub evidence: Vec<Evidence>,
+}
+
+/// Verifies the TabState file at `path`. On top of `verify_refs`, this compares the saved file's
+/// FILETIME with the TabState file's modified time, runs `verify_content_hash` if the saved file can
+/// be read, and if `windows` is provided, checks that one of them lists the tab. Only IO errors with
+/// the TabState file itself are returned as errors. A file that can't be parsed gets a
+/// `Verdict::Unreadable` report.
+#[cfg(feature = "std")]
+pub fn verify_file<P: AsRef<Path>>(
+    path: P,
+    windows: Option<&[WindowStateFile]>,
+) -> std::io::Result<VerifyReport> {
+    let path = path.as_ref();
+    let buffer = std::fs::read(path)?;
+    let fs_metadata = std::fs::metadata(path)?;
+
+    let refs = match TabStateRefs::from_buffer(&buffer[..]) {
+        Ok(refs) => refs,
+        Err(e) => {
+            return Ok(VerifyReport {
+                path: path.to_path_buf(),
+                verdict: Verdict::Unreadable,
+                evidence: vec![Evidence {
+                    check: Check::Parse,
+                    severity: Severity::Suspicious,
+                    field: None,
+                    offset: None,
+                    message: e.message().to_string(),
+                }],
+            });
+        }
+    };
+
+    let mut evidence = verify_refs(&refs);
+
+    // Impossible FILETIMEs are already evidence from `verify_refs`.
+    let filetime = refs
+        .get_metadata()
+        .and_then(|m| u64::try_from(m.get_filetime().decode_lossless()).ok())
+        .filter(|filetime| *filetime != 0 && *filetime <= i64::MAX as u64);
+    if let (Some(filetime), Ok(modified)) = (filetime, fs_metadata.modified()) {
+        let filetime = crate::filetime::filetime_to_unix_micros(filetime);
+        let modified = crate::timeline::system_time_to_unix_micros(modified);
+        if filetime > modified + FILETIME_TOLERANCE_MICROS {
+            evidence.push(Evidence {
+                check: Check::FileTime,
+                severity: Severity::Suspicious,
+                field: Some("metadata.filetime".to_string()),
+                offset: None,
+                message: format!(
+                    "The saved file was last written at {}, after the TabState file was last \
+                    modified at {}. Notepad writes the TabState when it sees the file change",
+                    crate::filetime::format_iso8601(filetime),
+                    crate::filetime::format_iso8601(modified)
+                ),
+            });
+        }
+    }
+
+    // The path is whatever the file says, so one that can't be read just isn't checked.
+    let saved = refs
+        .get_metadata()
+        .map(|m| String::from_utf16_lossy(&m.get_path_chars()))
+        .and_then(|path| std::fs::read(path).ok());
+    if let Some(saved) = saved {
+        evidence.extend(verify_content_hash(&refs, &saved, CodePage::default()));
+    }
+
+    if let Some(windows) = windows {
+        let guid = path
+            .file_stem()
+            .and_then(|stem| crate::windowstate::Guid::parse(&stem.to_string_lossy()));
+        let listed = guid.is_some_and(|guid| windows.iter().any(|w| w.window.tabs.contains(&guid)));
+        if !listed {
+            evidence.push(Evidence {
+                check: Check::WindowState,
+                severity: Severity::Suspicious,
+                field: None,
+                offset: None,
+                message: match guid {
+                    Some(guid) => format!("No WindowState file lists tab {guid}"),
+                    None => "The file name is not a tab GUID".to_string(),
+                },
+            });
+        }
+    }
+
+    Ok(VerifyReport {
+        path: path.to_path_buf(),
+        verdict: Verdict::from_evidence(&evidence),
+        evidence,
+    })
+}
+
+/// Verifies every TabState file in `tabstate_dir`. WindowState cross references are only checked if
+/// `windowstate_dir` is provided.
+#[cfg(feature = "std")]
+pub fn verify_dir<P: AsRef<Path>>(
+    tabstate_dir: P,
+    windowstate_dir: Option<&Path>,
+) -> std::io::Result<Vec<VerifyReport>> {
+    let windows = match windowstate_dir {
+        Some(dir) => Some(crate::dir::read_windowstate_dir(dir)?),
+        None => None,
+    };
+
+    get_tabstate_paths(tabstate_dir)?
+        .iter()
+        .map(|path| verify_file(path, windows.as_deref()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::test_util::saved_tab;
+    use crate::text::{hash_bytes, CodePage};
+    use crate::verify::{verify_content_hash, verify_refs, Check, Severity, Verdict};
+
+    #[test]
+    fn clean_file_is_consistent() {
+        let bytes = saved_tab().to_bytes();
+        let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+        let evidence = verify_refs(&refs);
+        assert!(evidence.is_empty());
+        assert_eq!(Verdict::from_evidence(&evidence), Verdict::Consistent);
+    }
+
+    #[test]
+    fn finds_edits() {
+        let mut tab = saved_tab();
+        tab.cursor_end = 50;
+        tab.crc = Some(0);
+        tab.metadata.as_mut().unwrap().full_buffer_size = Some(3);
+        let bytes = tab.to_bytes();
+
+        let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+        let evidence = verify_refs(&refs);
+        let found = evidence
+            .iter()
+            .map(|e| (e.check, e.severity, e.field.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (Check::Crc, Severity::Tampered, Some("footer.crc")),
+                (Check::CursorBounds, Severity::Tampered, Some("cursor.end")),
+                (
+                    Check::FullBufferSize,
+                    Severity::Note,
+                    Some("metadata.full_buffer_size")
+                ),
+            ]
+        );
+        assert_eq!(Verdict::from_evidence(&evidence), Verdict::Tampered);
+    }
+
+    #[test]
+    fn impossible_filetime_is_tampered() {
+        for filetime in [1 << 63, u64::MAX] {
+            let mut tab = saved_tab();
+            tab.metadata.as_mut().unwrap().filetime = filetime;
+            let bytes = tab.to_bytes();
+
+            let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+            let evidence = verify_refs(&refs);
+            assert_eq!(evidence.len(), 1);
+            assert_eq!(evidence[0].check, Check::FileTime);
+            assert_eq!(evidence[0].severity, Severity::Tampered);
+        }
+    }
+
+    #[test]
+    fn checks_content_hash() {
+        // The fixture is CRLF, so this is "Hello\rWorld" as Notepad would save it.
+        let disk = b"Hello\r\nWorld";
+        let mut tab = saved_tab();
+        let check = |tab: &crate::tabstate::TabState, disk: &[u8]| {
+            let bytes = tab.to_bytes();
+            let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+            verify_content_hash(&refs, disk, CodePage::default()).map(|e| e.severity)
+        };
+
+        tab.metadata.as_mut().unwrap().content_hash = hash_bytes(disk);
+        assert_eq!(check(&tab, disk), None);
+        // The file changed after Notepad saw it.
+        assert_eq!(check(&tab, b"Hello"), Some(Severity::Note));
+
+        // The text is the same as the file, but the hash isn't.
+        tab.metadata.as_mut().unwrap().content_hash = [0; 0x20];
+        assert_eq!(check(&tab, disk), Some(Severity::Tampered));
+    }
+
+    /// The file system checks need real files, so these go through a temp folder.
+    #[test]
+    fn checks_filetime_and_windowstate() {
+        use crate::verify::verify_dir;
+        use crate::windowstate::{Guid, WindowState};
+
+        let dir = std::env::temp_dir().join(format!("tabstate-verify-{}", std::process::id()));
+        let tab_dir = dir.join("TabState");
+        let window_dir = dir.join("WindowState");
+        std::fs::create_dir_all(&tab_dir).unwrap();
+        std::fs::create_dir_all(&window_dir).unwrap();
+
+        let listed = Guid([1; 0x10]);
+        let unlisted = Guid([2; 0x10]);
+        std::fs::write(
+            tab_dir.join(format!("{listed}.bin")),
+            saved_tab().to_bytes(),
+        )
+        .unwrap();
+        let mut future = saved_tab();
+        // Far in the future, so it's after the TabState file was written.
+        future.metadata.as_mut().unwrap().filetime = 0x0300_0000_0000_0000;
+        std::fs::write(tab_dir.join(format!("{unlisted}.bin")), future.to_bytes()).unwrap();
+
+        let window = WindowState {
+            sequence_number: 1,
+            bytes_to_crc: None,
+            unk: 0,
+            tabs: vec![listed],
+            active_tab: 0,
+            top_left: [0, 0],
+            bottom_right: [800, 600],
+            window_size: [800, 600],
+            unk2: 0,
+            crc: None,
+            slack: vec![],
+        };
+        std::fs::write(window_dir.join("window.0.bin"), window.to_bytes()).unwrap();
+
+        let reports = verify_dir(&tab_dir, Some(&window_dir)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let verdicts = reports
+            .iter()
+            .map(|r| {
+                let guid = r.path.file_stem().unwrap().to_string_lossy().into_owned();
+                let checks = r.evidence.iter().map(|e| e.check).collect::<Vec<_>>();
+                (guid, r.verdict, checks)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            verdicts,
+            [
+                (listed.to_string(), Verdict::Consistent, vec![]),
+                (
+                    unlisted.to_string(),
+                    Verdict::Suspicious,
+                    vec![Check::FileTime, Check::WindowState]
+                ),
+            ]
+        );
+    }
+}
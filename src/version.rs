@@ -0,0 +1,219 @@
+#![doc = "Detects which version of the TabState format a file is, before we try to parse it. Notepad keeps changing the format between releases, so this is where new layouts get picked."]
+
+use crate::consts::{CRC_START, FILE_STATE_SAVED, FILE_STATE_UNSAVED, MAGIC};
+use crate::crc::crc32;
+use crate::error::{Error, ErrorKind};
+use crate::spans::skim_field_spans;
+use crate::windowstate::WindowState;
+use alloc::format;
+use alloc::string::String;
+
+/// Offset of the version byte in the magic.
+const VERSION_OFFSET: usize = 0x2;
+
+/// The TabState layouts this crate knows how to parse.
+///
+/// The only thing I have to go on is the third byte of the magic. It has been 0 in every sample I
+/// have seen, but "NP" is the real magic, so I'd bet that byte is what changes when the layout does.
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormatVersion {
+    /// "NP\0": header, optional metadata, cursor, options, text, footer, then edit chunks.
+    V1,
+}
+
+impl FormatVersion {
+    /// The newest version we know about. This is what new files are written as.
+    pub const LATEST: Self = FormatVersion::V1;
+
+    /// The third byte of the magic for this version.
+    pub fn header_byte(&self) -> u8 {
+        match self {
+            FormatVersion::V1 => MAGIC[VERSION_OFFSET],
+        }
+    }
+    pub fn from_header_byte(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(FormatVersion::V1),
+            _ => None,
+        }
+    }
+}
+
+/// Picks the format version from the first bytes of the file. Only needs the magic, so it works for
+/// streams, too. Use `detect_version` if you have the whole file, for better errors.
+pub fn version_from_magic(magic: &[u8; 3]) -> crate::error::Result<FormatVersion> {
+    if magic[..VERSION_OFFSET] != MAGIC[..VERSION_OFFSET] {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Magic bytes invalid. Should start with \"NP\". Read: \"{}\" raw: {:?}",
+                String::from_utf8_lossy(magic),
+                magic
+            ),
+        ));
+    }
+
+    FormatVersion::from_header_byte(magic[VERSION_OFFSET]).ok_or_else(|| {
+        Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Unknown TabState format version {:02X}. This is probably from a newer Notepad. \
+                Please run `tabstate bug-report` on this file and open an issue!",
+                magic[VERSION_OFFSET]
+            ),
+        )
+    })
+}
+
+/// Detects the format version of a whole file. On top of the header byte, this probes the rest of the
+/// file: whether it's one of the other "NP" files Notepad writes, whether the state byte is one we
+/// know, and for unknown versions, whether the file is still laid out like `V1`. That way the error
+/// says what the file actually is, instead of whatever field the parser tripped over.
+pub fn detect_version(buffer: &[u8]) -> crate::error::Result<FormatVersion> {
+    let Some(magic) = buffer.first_chunk::<3>() else {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            format!(
+                "Buffer is too small for the magic bytes. Expected at least 3 bytes, got {}",
+                buffer.len()
+            ),
+        ));
+    };
+
+    // WindowState files also start with "NP", followed by a sequence number, so they can look like a
+    // TabState file of any version, including ones we know. Only call it one if the CRC checks out.
+    if WindowState::is_crc_valid(buffer) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "This is a WindowState file, not a TabState file. Use `WindowState::from_buffer`.",
+        ));
+    }
+
+    let version = match version_from_magic(magic) {
+        Ok(version) => version,
+        Err(e) if e.kind() == ErrorKind::Unsupported && has_v1_layout(buffer) => {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "{} The rest of the file is laid out like version {:02X} though, so the change \
+                    might be small.",
+                    e.message(),
+                    FormatVersion::V1.header_byte()
+                ),
+            ));
+        }
+        Err(e) => return Err(e),
+    };
+
+    // The `.0.bin` and `.1.bin` files next to a tab have the same header, but a state byte of their own.
+    if let Some(&state) = buffer.get(3) {
+        if state != FILE_STATE_SAVED && state != FILE_STATE_UNSAVED {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                format!(
+                    "Unknown file state {state:02X}. This is probably one of the `.0.bin` or `.1.bin` \
+                    files Notepad keeps next to a tab, which can't be parsed yet."
+                ),
+            ));
+        }
+    }
+
+    Ok(version)
+}
+
+/// Whether the fields `skim_field_spans` finds are followed by a footer with a CRC that checks out,
+/// like in a `V1` file. Edit chunks aren't looked at.
+fn has_v1_layout(buffer: &[u8]) -> bool {
+    let spans = skim_field_spans(buffer);
+    let Some(text) = spans.iter().find(|span| span.field == "text") else {
+        return false;
+    };
+    // The footer is a zero byte, then the CRC of everything since the magic.
+    let crc_start = text.end() + 1;
+    match buffer.get(crc_start..crc_start + 4) {
+        Some(crc) => *crc == crc32(&buffer[CRC_START..crc_start]).to_be_bytes(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::ErrorKind;
+    use crate::refs::tabstate::TabStateRefs;
+    use crate::test_util::unsaved_tab;
+    use crate::version::{detect_version, FormatVersion};
+    use crate::windowstate::{Guid, WindowState};
+
+    #[test]
+    fn detects_v1() {
+        let bytes = unsaved_tab().to_bytes();
+        assert_eq!(detect_version(&bytes).unwrap(), FormatVersion::V1);
+        let refs = TabStateRefs::from_buffer(&bytes).unwrap();
+        assert_eq!(refs.get_version(), FormatVersion::V1);
+        assert_eq!(refs.to_tab_state().version(), Some(FormatVersion::V1));
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        let mut tab = unsaved_tab();
+        tab.header_unk = 7;
+        let bytes = tab.to_bytes();
+        let error = TabStateRefs::from_buffer(&bytes).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+        assert!(error.message().contains("version 07"));
+        assert!(error.message().contains("laid out like version 00"));
+
+        // Once the footer CRC doesn't add up, there's no hint.
+        let mut bytes = bytes;
+        bytes[4] ^= 1;
+        let error = detect_version(&bytes).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+        assert!(!error.message().contains("laid out like"));
+
+        let error = detect_version(b"MZ\x90\x00").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        let error = detect_version(b"NP").unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn recognizes_windowstate() {
+        let window = WindowState {
+            sequence_number: 5,
+            bytes_to_crc: None,
+            unk: 0,
+            tabs: vec![Guid([1; 0x10])],
+            active_tab: 0,
+            top_left: [0, 0],
+            bottom_right: [800, 600],
+            window_size: [800, 600],
+            unk2: 0,
+            crc: None,
+            slack: vec![],
+        };
+        let error = detect_version(&window.to_bytes()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.message().contains("WindowState"));
+
+        // The sequence number is a varint right after "NP", so 0 makes it look like version 0.
+        let window = WindowState {
+            sequence_number: 0,
+            ..window
+        };
+        let bytes = window.to_bytes();
+        assert_eq!(bytes[2], 0);
+        let error = detect_version(&bytes).unwrap_err();
+        assert!(error.message().contains("WindowState"));
+    }
+
+    #[test]
+    fn probes_the_state() {
+        let mut bytes = unsaved_tab().to_bytes();
+        bytes[3] = 2;
+        let error = detect_version(&bytes).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+        assert!(error.message().contains(".0.bin"));
+    }
+}
@@ -0,0 +1,121 @@
+#![doc = "JavaScript exports for the browser and Node. Every function takes the file as a `Uint8Array`, and hands back plain JS objects with the same shape as the JSON export."]
+
+use crate::spans::get_field_spans;
+use crate::tabstate::TabState;
+use crate::windowstate::WindowState;
+use alloc::string::{String, ToString};
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+/// The `u64` fields, which always come back as a `BigInt`. Everything else is a `usize` or smaller,
+/// and a wasm32 `usize` fits in a JS number.
+const BIGINT_FIELDS: [&str; 1] = ["filetime"];
+
+/// Parses a TabState file. Throws if the file can't be parsed.
+#[wasm_bindgen(js_name = parseTabState)]
+pub fn parse_tabstate(data: &[u8]) -> Result<JsValue, JsError> {
+    let tab = TabState::from_buffer(data).map_err(|e| JsError::new(e.message()))?;
+    to_js(&tab)
+}
+
+/// Parses a WindowState file. Throws if the file can't be parsed.
+#[wasm_bindgen(js_name = parseWindowState)]
+pub fn parse_windowstate(data: &[u8]) -> Result<JsValue, JsError> {
+    let window = WindowState::from_buffer(data).map_err(|e| JsError::new(e.message()))?;
+    to_js(&window)
+}
+
+/// Parses a TabState file and returns the text with every edit chunk applied.
+#[wasm_bindgen(js_name = reconstructText)]
+pub fn reconstruct_text(data: &[u8]) -> Result<String, JsError> {
+    let tab = TabState::from_buffer(data).map_err(|e| JsError::new(e.message()))?;
+    Ok(tab.reconstruct_text())
+}
+
+/// Returns the field map of a TabState file, for hex views.
+#[wasm_bindgen(js_name = getFieldSpans)]
+pub fn field_spans(data: &[u8]) -> Result<JsValue, JsError> {
+    let spans = get_field_spans(data).map_err(|e| JsError::new(e.message()))?;
+    to_js(&spans)
+}
+
+/// Goes through `serde_json::Value` instead of straight to JS, so we can keep the FILETIME exact.
+/// The fields in `BIGINT_FIELDS` are always a `BigInt`, whatever their value, so callers don't have
+/// to check the type.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    let value = serde_json::to_value(value).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(json_to_js(&value, false))
+}
+
+fn json_to_js(value: &Value, bigint: bool) -> JsValue {
+    match value {
+        Value::Null => JsValue::NULL,
+        Value::Bool(b) => JsValue::from_bool(*b),
+        Value::Number(n) => match n.as_u64() {
+            Some(n) if bigint => js_sys::BigInt::from(n).into(),
+            _ => JsValue::from_f64(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => JsValue::from_str(s),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| json_to_js(value, false))
+            .collect::<js_sys::Array>()
+            .into(),
+        Value::Object(map) => {
+            let object = js_sys::Object::new();
+            for (key, value) in map {
+                let value = json_to_js(value, BIGINT_FIELDS.contains(&key.as_str()));
+                // Only fails if `object` isn't an object.
+                let _ = js_sys::Reflect::set(&object, &JsValue::from_str(key), &value);
+            }
+            object.into()
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use crate::tabstate::TabState;
+    use crate::test_util::{saved_tab, unsaved_tab};
+    use crate::wasm::{parse_tabstate, reconstruct_text};
+    use wasm_bindgen::JsValue;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn tab() -> TabState {
+        TabState {
+            cursor_start: 1,
+            cursor_end: 2,
+            chunks: vec![],
+            ..unsaved_tab()
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn parses_to_object() {
+        let value = parse_tabstate(&tab().to_bytes()).unwrap();
+        let text = js_sys::Reflect::get(&value, &JsValue::from_str("text")).unwrap();
+        assert_eq!(text.as_string().as_deref(), Some("Hello"));
+        let cursor = js_sys::Reflect::get(&value, &JsValue::from_str("cursor_end")).unwrap();
+        assert_eq!(cursor.as_f64(), Some(2.0));
+        assert_eq!(reconstruct_text(&tab().to_bytes()).unwrap(), "Hello");
+    }
+
+    #[wasm_bindgen_test]
+    fn filetime_is_always_a_bigint() {
+        for filetime in [1, u64::MAX] {
+            let mut tab = saved_tab();
+            tab.metadata.as_mut().unwrap().filetime = filetime;
+            let value = parse_tabstate(&tab.to_bytes()).unwrap();
+            let metadata = js_sys::Reflect::get(&value, &JsValue::from_str("metadata")).unwrap();
+            let value = js_sys::Reflect::get(&metadata, &JsValue::from_str("filetime")).unwrap();
+            assert!(value.is_bigint());
+            assert_eq!(value, JsValue::from(js_sys::BigInt::from(filetime)));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn bad_file_is_an_error() {
+        assert!(parse_tabstate(b"NOPE").is_err());
+    }
+}
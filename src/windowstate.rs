@@ -0,0 +1,277 @@
+#![doc = "WindowState files. Each Notepad window has one, listing the tabs that are open in it, in order, along with where the window is on screen."]
+
+use crate::consts::CRC_START;
+use crate::crc::crc32;
+use crate::error::{Error, ErrorKind};
+use crate::reader::BufferReader;
+use crate::refs::varint::VarIntRef;
+use crate::tabstate::write_varint;
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The GUID of a tab. TabState files are named after the GUID of the tab they belong to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Guid(pub [u8; 0x10]);
+
+impl Guid {
+    /// Parses a GUID in the same format `Display` writes, with or without braces.
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim_start_matches('{').trim_end_matches('}');
+        let hex = s.replace('-', "");
+        if s.len() != 36 || hex.len() != 32 || !hex.is_ascii() {
+            return None;
+        }
+
+        let mut be = [0u8; 0x10];
+        for (i, byte) in be.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        // The first three groups are stored little endian.
+        be[0..4].reverse();
+        be[4..6].reverse();
+        be[6..8].reverse();
+
+        Some(Self(be))
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:02x}{:02x}-{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_le_bytes([b[4], b[5]]),
+            u16::from_le_bytes([b[6], b[7]]),
+            b[8],
+            b[9],
+            b[10],
+            b[11],
+            b[12],
+            b[13],
+            b[14],
+            b[15]
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Guid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Guid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = alloc::string::String::deserialize(deserializer)?;
+        Guid::parse(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid GUID: {s:?}")))
+    }
+}
+
+/// An owned WindowState file. Notepad keeps two of these per window, `{guid}.0.bin` and
+/// `{guid}.1.bin`, and writes to them in turn. The one with the higher sequence number is the newest.
+///
+/// As far as I can tell the layout is: "NP", sequence number varint, a varint count of bytes up to
+/// the CRC, a zero byte, tab count varint, the tab GUIDs, active tab varint, the window rect and size
+/// as u32s, another zero byte and a big endian CRC32. Anything after the CRC is left over from older
+/// writes.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowState {
+    pub sequence_number: usize,
+    /// Bytes from after this field up to the CRC. Calculated when written if `None`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub bytes_to_crc: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unk: u8,
+    /// The tabs in the window, in the order they are shown.
+    pub tabs: Vec<Guid>,
+    /// Index into `tabs` of the selected tab.
+    pub active_tab: usize,
+    /// Top left corner of the window, in screen coordinates.
+    pub top_left: [u32; 2],
+    /// Bottom right corner of the window, in screen coordinates.
+    pub bottom_right: [u32; 2],
+    /// Width and height of the window.
+    pub window_size: [u32; 2],
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unk2: u8,
+    /// CRC32 of everything after the "NP" magic up to the CRC.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub crc: Option<u32>,
+    /// Stale bytes after the CRC.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub slack: Vec<u8>,
+}
+
+impl WindowState {
+    /// Parses a WindowState file from a given buffer. The CRC is kept as it was in the file, so use
+    /// `is_crc_valid` on the buffer to check it.
+    pub fn from_buffer(buffer: &[u8]) -> crate::error::Result<Self> {
+        let mut br = BufferReader::new(buffer);
+        let magic = br.read_bytes(2)?;
+        if magic != b"NP" {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid magic bytes for WindowState file: {magic:02X?}"),
+            ));
+        }
+
+        let sequence_number = VarIntRef::from_reader(&mut br)?.decode();
+        let bytes_to_crc = VarIntRef::from_reader(&mut br)?.decode();
+        let unk = *br.read_t::<u8>()?;
+
+        let tab_count = VarIntRef::from_reader(&mut br)?.decode();
+        // Check the count against what is left, so a bad count can't make us allocate a ton.
+        if tab_count > br.len() / 0x10 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Tab count {tab_count} is larger than the rest of the WindowState file: {}",
+                    br.len()
+                ),
+            ));
+        }
+        let mut tabs = Vec::with_capacity(tab_count);
+        for _ in 0..tab_count {
+            tabs.push(Guid(*br.read_t::<[u8; 0x10]>()?));
+        }
+
+        let active_tab = VarIntRef::from_reader(&mut br)?.decode();
+        let mut read_u32 = || -> crate::error::Result<u32> {
+            Ok(u32::from_le_bytes(*br.read_t::<[u8; 4]>()?))
+        };
+        let top_left = [read_u32()?, read_u32()?];
+        let bottom_right = [read_u32()?, read_u32()?];
+        let window_size = [read_u32()?, read_u32()?];
+        let unk2 = *br.read_t::<u8>()?;
+        let crc = u32::from_be_bytes(*br.read_t::<[u8; 4]>()?);
+        let slack = br.read_bytes(br.len())?.to_vec();
+
+        Ok(Self {
+            sequence_number,
+            bytes_to_crc: Some(bytes_to_crc),
+            unk,
+            tabs,
+            active_tab,
+            top_left,
+            bottom_right,
+            window_size,
+            unk2,
+            crc: Some(crc),
+            slack,
+        })
+    }
+    /// The GUID of the selected tab, if the index is in range.
+    pub fn get_active_tab(&self) -> Option<&Guid> {
+        self.tabs.get(self.active_tab)
+    }
+    /// Writes this `WindowState` out as a WindowState buffer. Any length or CRC that is `None` is
+    /// calculated.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut body = vec![self.unk];
+        write_varint(&mut body, self.tabs.len());
+        for tab in &self.tabs {
+            body.extend_from_slice(&tab.0);
+        }
+        write_varint(&mut body, self.active_tab);
+        for value in self.top_left.iter().chain(&self.bottom_right).chain(&self.window_size) {
+            body.extend_from_slice(&value.to_le_bytes());
+        }
+        body.push(self.unk2);
+
+        let mut buffer = b"NP".to_vec();
+        write_varint(&mut buffer, self.sequence_number);
+        write_varint(&mut buffer, self.bytes_to_crc.unwrap_or(body.len()));
+        buffer.extend_from_slice(&body);
+
+        let crc = self.crc.unwrap_or_else(|| crc32(&buffer[CRC_START..]));
+        buffer.extend_from_slice(&crc.to_be_bytes());
+        buffer.extend_from_slice(&self.slack);
+
+        buffer
+    }
+    /// Checks the CRC in the provided WindowState buffer. The slack after the CRC isn't covered.
+    pub fn is_crc_valid(buffer: &[u8]) -> bool {
+        let Ok(window) = Self::from_buffer(buffer) else {
+            return false;
+        };
+        let crc_end = buffer.len() - window.slack.len();
+        let crc_start = crc_end - 4;
+
+        window.crc == Some(crc32(&buffer[CRC_START..crc_start]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::windowstate::{Guid, WindowState};
+
+    const GUID: &str = "0c07e304-0604-4438-941d-0977da045fd9";
+
+    fn window() -> WindowState {
+        WindowState {
+            sequence_number: 0x1F4,
+            bytes_to_crc: None,
+            unk: 0,
+            tabs: vec![Guid::parse(GUID).unwrap(), Guid([0x11; 0x10])],
+            active_tab: 1,
+            top_left: [100, 200],
+            bottom_right: [900, 800],
+            window_size: [800, 600],
+            unk2: 0,
+            crc: None,
+            slack: vec![],
+        }
+    }
+
+    #[test]
+    fn guid_round_trip() {
+        let guid = Guid::parse(GUID).unwrap();
+        assert_eq!(guid.0[0], 0x04);
+        assert_eq!(guid.to_string(), GUID);
+        assert_eq!(Guid::parse(&format!("{{{GUID}}}")), Some(guid));
+        assert_eq!(Guid::parse("not a guid"), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        let window = window();
+        let mut bytes = window.to_bytes();
+        assert!(WindowState::is_crc_valid(&bytes));
+
+        bytes.extend_from_slice(&[0xAA; 3]);
+        let parsed = WindowState::from_buffer(&bytes).unwrap();
+        assert_eq!(parsed.tabs, window.tabs);
+        assert_eq!(parsed.get_active_tab(), window.tabs.get(1));
+        assert_eq!(parsed.window_size, [800, 600]);
+        assert_eq!(parsed.slack, vec![0xAA; 3]);
+        assert!(WindowState::is_crc_valid(&bytes));
+        assert_eq!(parsed.to_bytes(), bytes);
+
+        bytes[5] ^= 0xFF;
+        assert!(!WindowState::is_crc_valid(&bytes));
+    }
+
+    #[test]
+    fn bad_tab_count() {
+        // "NP", seq 1, bytes to crc 1, zero, then a tab count that is way too large.
+        let bytes = [b'N', b'P', 1, 1, 0, 0xFF, 0x7F];
+        assert!(WindowState::from_buffer(&bytes).is_err());
+    }
+}
@@ -0,0 +1,3 @@
+#![doc = "The wasm-pack build of tabstate-util. The exports are in the main crate, behind the `wasm` feature, and this only links them into a cdylib."]
+
+pub use tabstate::wasm::*;